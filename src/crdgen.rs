@@ -1,5 +1,32 @@
 use kube::CustomResourceExt;
 
-fn main() {
-    print!{"{}", serde_yaml::to_string(&operator::Application::crd()).unwrap()}
+/// The Application CRD as YAML
+fn crd_yaml() -> Result<String, serde_yaml::Error> {
+    serde_yaml::to_string(&operator::Application::crd())
+}
+
+/// Print the Application CRD, e.g. `cargo run --bin crdgen | kubectl apply -f -`
+fn main() -> Result<(), serde_yaml::Error> {
+    print!("{}", crd_yaml()?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+
+    #[test]
+    fn crd_yaml_round_trips() {
+        let crd: CustomResourceDefinition = serde_yaml::from_str(&crd_yaml().unwrap()).unwrap();
+        assert_eq!(crd.metadata.name.as_deref(), Some("applications.per.naess"));
+        assert_eq!(crd.spec.group, "per.naess");
+        assert_eq!(crd.spec.names.kind, "Application");
+        let version = &crd.spec.versions[0];
+        assert_eq!(version.name, "v1alpha1");
+        let subresources = version.subresources.as_ref().unwrap();
+        assert!(subresources.status.is_some());
+        assert_eq!(subresources.scale.as_ref().unwrap().spec_replicas_path, ".spec.replicas");
+        assert_eq!(crd, operator::Application::crd());
+    }
 }
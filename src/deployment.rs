@@ -1,80 +1,1255 @@
-use k8s_openapi::api::apps::v1::Deployment;
-use kube::{api::{PostParams, DeleteParams}, ResourceExt, Client, Api}; 
+use k8s_openapi::{apimachinery::pkg::apis::meta::v1::OwnerReference, api::{apps::v1::Deployment, autoscaling::v2::HorizontalPodAutoscaler, core::v1::{Pod, Service}, networking::v1::Ingress, policy::v1::PodDisruptionBudget}};
+use kube::{api::{DeleteParams, ListParams, Patch, PatchParams}, runtime::{events::{Event, EventType, Recorder}, wait::{await_condition, Condition}}, Client, Api, Resource, ResourceExt}; 
+use serde::de::DeserializeOwned;
+use std::{collections::BTreeMap, fmt::Debug, future::Future, time::Duration};
+
 use serde_json::json;
-use tracing::info;
+use prometheus::IntCounterVec;
+use governor::{clock::DefaultClock, state::{InMemoryState, NotKeyed}, RateLimiter};
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument, warn};
+
+use crate::operator::{reasons, Application, ApplicationSpec, AutoscalingSpec, ContainerSpec, ExposeSpec, IngressSpec, PdbSpec};
+
+/// Replica count used when the spec does not set one
+pub const DEFAULT_REPLICAS: i32 = 1;
+
+/// CPU utilization targeted when `target_cpu_utilization` is unset
+pub const DEFAULT_TARGET_CPU_UTILIZATION: i32 = 80;
+
+/// Attempts at applying a Deployment before a transient error fails the reconcile
+const PATCH_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled for every further attempt
+const PATCH_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// How long `create_deployment` waits for a new rollout to become available
+const READY_WAIT: Duration = Duration::from_secs(10);
+
+/// Name of the pod volume backed by `spec.config_mount`
+pub const CONFIG_VOLUME: &str = "config";
+
+/// Field manager of everything the operator server-side applies
+pub const FIELD_MANAGER: &str = "cntrlr";
+
+/// Value of the `app.kubernetes.io/managed-by` label on managed resources
+pub const MANAGED_BY: &str = "customapps-operator";
+
+/// Annotation naming the Application, as `namespace/name`, that manages a child
+///
+/// Owner references cannot cross namespaces, so children in a target namespace are only recognised by it
+pub const OWNER_ANNOTATION: &str = "customapps.per.naess/owner";
+
+/// Annotation overriding `spec.replicas`, e.g. during incident response
+pub const REPLICAS_OVERRIDE_ANNOTATION: &str = "customapps.per.naess/replicas-override";
+
+/// Replicas of the Deployment and where they came from
+///
+/// The HorizontalPodAutoscaler owns replicas when there is one, otherwise a valid
+/// override annotation wins over `spec.replicas`
+pub fn desired_replicas(app: &Application) -> (Option<i32>, &'static str) {
+    if app.spec.autoscaling.is_some() {
+        return (None, "Autoscaling");
+    }
+    let replicas_override = app.annotations()
+        .get(REPLICAS_OVERRIDE_ANNOTATION)
+        .and_then(|replicas| replicas.parse::<i32>().ok())
+        .filter(|replicas| *replicas >= 0);
+    match (replicas_override, app.spec.replicas) {
+        (Some(replicas), _) => (Some(replicas), "Annotation"),
+        (None, Some(replicas)) => (Some(replicas), "Spec"),
+        (None, None) => (Some(DEFAULT_REPLICAS), "Default"),
+    }
+}
+
+/// Labels shared by all resources of an Application, also used as pod selector
+pub fn labels(application_spec: &ApplicationSpec) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("app.kubernetes.io/name".to_string(), application_spec.name.clone()),
+        ("app.kubernetes.io/managed-by".to_string(), MANAGED_BY.to_string()),
+    ])
+}
+
+/// User supplied labels merged with the managed `labels`, which win on conflict
+pub fn resource_labels(application_spec: &ApplicationSpec) -> BTreeMap<String, String> {
+    resource_labels_with(application_spec, None)
+}
+
+/// `resource_labels` with `extra` labels for a single resource on top of the shared ones
+fn resource_labels_with(application_spec: &ApplicationSpec, extra: Option<&BTreeMap<String, String>>) -> BTreeMap<String, String> {
+    let mut merged = application_spec.labels.clone().unwrap_or_default();
+    merged.extend(extra.cloned().unwrap_or_default());
+    merged.extend(labels(application_spec));
+    merged
+}
+
+/// `namespace/name` of an Application, the value of its children's `OWNER_ANNOTATION`
+fn owner_key(app: &Application) -> String {
+    format!("{}/{}", app.namespace().unwrap_or_default(), app.name_any())
+}
+
+/// User supplied annotations with the `OWNER_ANNOTATION` of `app`
+fn resource_annotations(app: &Application) -> BTreeMap<String, String> {
+    let mut annotations = app.spec.annotations.clone().unwrap_or_default();
+    annotations.insert(OWNER_ANNOTATION.into(), owner_key(app));
+    annotations
+}
+
+/// Whether `child` was created for `app` and may be applied over or deleted
+///
+/// It needs our `managed-by` label and either our `OWNER_ANNOTATION` or an owner reference to `app`,
+/// the latter covering children created before the annotation was introduced
+pub fn is_managed_by<K: Resource>(child: &K, app: &Application) -> bool {
+    let managed = child.labels().get("app.kubernetes.io/managed-by").map(String::as_str) == Some(MANAGED_BY);
+    let annotated = child.annotations().get(OWNER_ANNOTATION) == Some(&owner_key(app));
+    let referenced = app.uid().is_some_and(|uid| child.owner_references().iter().any(|o| o.uid == uid));
+    managed && (annotated || referenced)
+}
+
+/// Pod template annotation rolling the pods whenever the spec changes
+pub const SPEC_CHECKSUM_ANNOTATION: &str = "per.naess/spec-checksum";
+
+/// Hex SHA-256 of the spec, ignoring fields that do not change the pods
+pub fn spec_checksum(application_spec: &ApplicationSpec) -> String {
+    let spec = ApplicationSpec {
+        replicas: None,
+        autoscaling: None,
+        pdb: None,
+        ..application_spec.clone()
+    };
+    let spec = serde_json::to_vec(&spec).expect("ApplicationSpec serializes to json");
+    Sha256::digest(spec).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Pod container for a sidecar or init container
+fn container_json(container: &ContainerSpec) -> serde_json::Value {
+    json!({
+        "name": container.name,
+        "image": container.image,
+        "command": container.command,
+        "args": container.args,
+        "ports": container.ports.as_ref()
+            .map(|ports| ports.iter().map(|port| json!({ "containerPort": port })).collect::<Vec<_>>())
+    })
+}
+
+/// Operator wide limit on mutating apiserver calls
+pub type ApiRateLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+
+/// Client and settings every call managing the children of an Application goes through
+pub struct ChildApi<'a> {
+    pub client: Client,
+    /// Apiserver calls, counted by verb and resource
+    pub calls: &'a IntCounterVec,
+    /// Limits mutating calls per second, unlimited when `None`
+    pub limiter: Option<&'a ApiRateLimiter>,
+    /// Send mutating calls as dry run
+    pub dry_run: bool,
+}
+
+/// Wait for `limiter` to allow another mutating call
+async fn throttle(limiter: Option<&ApiRateLimiter>) {
+    if let Some(limiter) = limiter {
+        limiter.until_ready().await;
+    }
+}
+
+/// Count an apiserver call on `calls`, labelled by verb and resource
+pub fn count_call<K: Resource<DynamicType = ()>>(calls: &IntCounterVec, verb: &str) {
+    calls.with_label_values(&[verb, &K::plural(&())]).inc();
+}
+
+/// Delete a child resource of `app`, returning false when it did not exist
+///
+/// A child deleted concurrently, e.g. garbage collected through its owner reference, counts as not existing.
+/// So does an object with the child's name that is not managed by `app`, it is left alone.
+async fn delete_child<K>(api: &Api<K>, app: &Application, kind: &str, child_api: &ChildApi<'_>) -> Result<bool, kube::Error>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug,
+{
+    let name = &app.spec.name;
+    count_call::<K>(child_api.calls, "get");
+    match api.get_opt(name).await? {
+        None => return Ok(false),
+        Some(child) if !is_managed_by(&child, app) => {
+            warn!("Leaving {} {}, it is not managed by Application {}", kind, name, owner_key(app));
+            return Ok(false);
+        },
+        Some(_) => (),
+    }
+
+    info!("Cleaning up {} {}", kind, name);
+    count_call::<K>(child_api.calls, "delete");
+    throttle(child_api.limiter).await;
+    match api.delete(name, &DeleteParams { dry_run: child_api.dry_run, ..DeleteParams::default() }).await {
+        Ok(deleted) => {
+            deleted
+                .map_left(|_| info!("Deleting {} {}", kind, name))
+                .map_right(|s| info!("Deleted {}{}: {:?}", kind, dry_run_suffix(child_api.dry_run), s));
+            Ok(true)
+        },
+        Err(kube::Error::Api(e)) if e.code == 404 => {
+            info!("{} {} already deleted", kind, name);
+            Ok(false)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Controller reference to `app` for children in `ns`
+///
+/// Owner references cannot cross namespaces, children elsewhere are only removed by the finalizer
+fn owner_references(app: &Application, ns: &str) -> Option<Vec<OwnerReference>> {
+    if app.namespace().as_deref() != Some(ns) {
+        return None;
+    }
+    app.controller_owner_ref(&()).map(|o| vec![o])
+}
+
+/// Message naming a `kind` object called `name` in `ns` that exists but is not managed by `app`
+async fn foreign_child<K>(ns: &str, app: &Application, kind: &str, child_api: &ChildApi<'_>) -> Result<Option<String>, kube::Error>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug,
+{
+    let api: Api<K> = Api::namespaced(child_api.client.clone(), ns);
+    count_call::<K>(child_api.calls, "get");
+    let foreign = api.get_opt(&app.spec.name).await?.filter(|child| !is_managed_by(child, app));
+    Ok(foreign.map(|_| format!("{} `{}` in namespace `{}` exists and is not managed by this Application", kind, app.spec.name, ns)))
+}
+
+/// Message naming the first object the spec of `app` would apply over but does not manage, if any
+///
+/// Server-side apply is forced, without this check an Application could take over any object it can name
+pub async fn foreign_children(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<Option<String>, kube::Error> {
+    let spec = &app.spec;
+    if !spec.deploy {
+        return Ok(None);
+    }
+    if let Some(foreign) = foreign_child::<Deployment>(ns, app, "Deployment", child_api).await? {
+        return Ok(Some(foreign));
+    }
+    if spec.expose.is_some() {
+        if let Some(foreign) = foreign_child::<Service>(ns, app, "Service", child_api).await? {
+            return Ok(Some(foreign));
+        }
+    }
+    if spec.expose.is_some() && spec.ingress.is_some() {
+        if let Some(foreign) = foreign_child::<Ingress>(ns, app, "Ingress", child_api).await? {
+            return Ok(Some(foreign));
+        }
+    }
+    if spec.autoscaling.is_some() {
+        if let Some(foreign) = foreign_child::<HorizontalPodAutoscaler>(ns, app, "HorizontalPodAutoscaler", child_api).await? {
+            return Ok(Some(foreign));
+        }
+    }
+    if spec.pdb.is_some() {
+        if let Some(foreign) = foreign_child::<PodDisruptionBudget>(ns, app, "PodDisruptionBudget", child_api).await? {
+            return Ok(Some(foreign));
+        }
+    }
+    Ok(None)
+}
+
+/// Publish `event` on `recorder`, in dry run it is only logged
+///
+/// Dry run never writes the status that events are deduplicated against, so they would repeat on every reconcile
+pub async fn publish(recorder: &Recorder, dry_run: bool, event: Event) -> Result<(), kube::Error> {
+    if dry_run {
+        info!("Dry run, not publishing {} event: {}", event.reason, event.note.unwrap_or_default());
+        return Ok(());
+    }
+    recorder.publish(event).await
+}
 
-use crate::operator::ApplicationSpec;
+/// Marks log lines of requests the apiserver did not persist
+fn dry_run_suffix(dry_run: bool) -> &'static str {
+    if dry_run { " (dry run)" } else { "" }
+}
 
 pub enum ApplicationDeploymentState {
     Deployed,
     Failed
 }
 
-pub async fn create_deployment(application_spec: &ApplicationSpec, ns: &str, client: Client) -> Result<(), kube::Error> {
-    info!("Creating deployment for {}", application_spec.name);
-    let deployments: Api<Deployment> = Api::namespaced(client, ns);
-    let deployment: Deployment = serde_json::from_value(json!({
+/// Desired Deployment of an Application with children in `ns`, running `default_image` when it sets no image
+pub fn deployment_for(app: &Application, default_image: Option<&str>, ns: &str) -> Deployment {
+    let application_spec = &app.spec;
+    let labels = labels(application_spec);
+    let deployment_labels = resource_labels_with(application_spec, application_spec.deployment_labels.as_ref());
+    let pod_labels = resource_labels_with(application_spec, application_spec.pod_labels.as_ref());
+    let (replicas, _) = desired_replicas(app);
+    // `null` values are dropped when deserializing into the Deployment
+    let ports = application_spec.port.map(|port| json!([{ "containerPort": port }]));
+    let env = application_spec.env.as_ref().filter(|env| !env.is_empty());
+    let image_pull_secrets = application_spec.image_pull_secrets.as_ref()
+        .filter(|secrets| !secrets.is_empty())
+        .map(|secrets| secrets.iter().map(|name| json!({ "name": name })).collect::<Vec<_>>());
+    let volumes: Vec<_> = application_spec.config_mount.as_ref()
+        .map(|mount| json!({ "name": CONFIG_VOLUME, "configMap": { "name": mount.config_map } }))
+        .into_iter()
+        .chain(application_spec.volumes.iter().flatten().map(|volume| json!(volume)))
+        .collect();
+    let volumes = Some(volumes).filter(|volumes| !volumes.is_empty());
+    let volume_mounts: Vec<_> = application_spec.config_mount.as_ref()
+        .map(|mount| json!({ "name": CONFIG_VOLUME, "mountPath": mount.mount_path, "readOnly": true }))
+        .into_iter()
+        .chain(application_spec.volume_mounts.iter().flatten().map(|mount| json!(mount)))
+        .collect();
+    let volume_mounts = Some(volume_mounts).filter(|mounts| !mounts.is_empty());
+    let container = json!({
+        "name": application_spec.container_name(),
+        "image": application_spec.image(default_image),
+        "imagePullPolicy": application_spec.image_pull_policy,
+        "command": application_spec.command,
+        "args": application_spec.args,
+        "ports": ports,
+        "env": env,
+        "resources": application_spec.resources,
+        "livenessProbe": application_spec.liveness_probe,
+        "readinessProbe": application_spec.readiness_probe,
+        "securityContext": application_spec.security_context,
+        "volumeMounts": volume_mounts
+    });
+    let sidecars = application_spec.sidecars.iter().flatten().map(container_json);
+    let containers: Vec<_> = std::iter::once(container).chain(sidecars).collect();
+    let init_containers = application_spec.init_containers.as_ref()
+        .filter(|containers| !containers.is_empty())
+        .map(|containers| containers.iter().map(container_json).collect::<Vec<_>>());
+    let mut template_annotations = application_spec.annotations.clone().unwrap_or_default();
+    if application_spec.roll_on_config_change {
+        template_annotations.insert(SPEC_CHECKSUM_ANNOTATION.into(), spec_checksum(application_spec));
+    }
+    serde_json::from_value(json!({
         "apiVersion": "apps/v1",
         "kind": "Deployment",
         "metadata": {
             "name": application_spec.name,
-            "labels": {
-                "app": "nginx"
-            }
+            // Let kubernetes garbage collect children of a deleted Application
+            "ownerReferences": owner_references(app, ns),
+            "labels": deployment_labels,
+            "annotations": resource_annotations(app)
         },
         "spec": {
-            "replicas": 2,
+            "replicas": replicas,
+            "strategy": application_spec.strategy,
+            "revisionHistoryLimit": application_spec.revision_history_limit,
+            "progressDeadlineSeconds": application_spec.progress_deadline_seconds,
             "selector": {
-                "matchLabels": {
-                    "app": "nginx"
-                }
+                "matchLabels": labels
             },
             "template": {
                 "metadata": {
-                    "labels": {
-                        "app": "nginx"
-                    }
+                    "labels": pod_labels,
+                    "annotations": template_annotations
                 },
                 "spec": {
-                    "containers": [{
-                        "name": application_spec.name,
-                        "image": application_spec.image
-                    }]
+                    "serviceAccountName": application_spec.service_account,
+                    "automountServiceAccountToken": application_spec.automount_service_account_token,
+                    "securityContext": application_spec.pod_security_context,
+                    "terminationGracePeriodSeconds": application_spec.termination_grace_period_seconds,
+                    "imagePullSecrets": image_pull_secrets,
+                    "nodeSelector": application_spec.node_selector,
+                    "tolerations": application_spec.tolerations,
+                    "affinity": application_spec.affinity,
+                    "topologySpreadConstraints": application_spec.topology_spread_constraints,
+                    "volumes": volumes,
+                    "initContainers": init_containers,
+                    "containers": containers
                 }
             }
         }
-    })).expect("Something is wrong with the deployment");
+    })).expect("Something is wrong with the deployment")
+}
+
+/// Server-side apply the desired Deployment for an Application and return the live object
+///
+/// Publishes an event on `recorder` when the Deployment did not exist yet
+#[instrument(skip_all, fields(app = %app.spec.name, namespace = %ns))]
+pub async fn create_deployment(app: &Application, default_image: Option<&str>, ns: &str, child_api: &ChildApi<'_>, recorder: &Recorder) -> Result<Deployment, kube::Error> {
+    let application_spec = &app.spec;
+    info!("Applying deployment for {}", application_spec.name);
+    let deployments: Api<Deployment> = Api::namespaced(child_api.client.clone(), ns);
+    if app.annotations().contains_key(REPLICAS_OVERRIDE_ANNOTATION) && desired_replicas(app).1 != "Annotation" {
+        warn!("Ignoring invalid {} annotation on {}", REPLICAS_OVERRIDE_ANNOTATION, application_spec.name);
+    }
+    let mut deployment = deployment_for(app, default_image, ns);
+
+    count_call::<Deployment>(child_api.calls, "get");
+    let existing = deployments.get_opt(&application_spec.name).await?;
+    let exists = existing.is_some();
+    // Dropping replicas from the apply while still owning them resets them to 1,
+    // keep them at their live value until the HorizontalPodAutoscaler scaled and took them over
+    if let Some(existing) = existing.as_ref().filter(|d| desired_replicas(app).0.is_none() && applies_replicas(d)) {
+        let replicas = existing.spec.as_ref().and_then(|spec| spec.replicas);
+        deployment.spec.get_or_insert_with(Default::default).replicas = replicas;
+    }
+    let ps = PatchParams { dry_run: child_api.dry_run, ..PatchParams::apply(FIELD_MANAGER).force() };
+    let (api, desired, ps) = (&deployments, &deployment, &ps);
+    let mut deployment = with_retries(&format!("deployment {}", application_spec.name), || async move {
+        count_call::<Deployment>(child_api.calls, "patch");
+        throttle(child_api.limiter).await;
+        api.patch(&application_spec.name, ps, &Patch::Apply(desired)).await
+    })
+    .await?;
+    info!("Applied deployment {}{}", application_spec.name, dry_run_suffix(child_api.dry_run));
+
+    // Give a new rollout a moment, so the state reported afterwards is not always Starting
+    let rolled_out = existing.and_then(|d| d.metadata.generation) != deployment.metadata.generation;
+    if rolled_out && !child_api.dry_run {
+        let desired = deployment.spec.as_ref().and_then(|s| s.replicas).unwrap_or(DEFAULT_REPLICAS);
+        count_call::<Deployment>(child_api.calls, "watch");
+        let available = await_condition(deployments.clone(), &application_spec.name, is_deployment_available(desired));
+        match tokio::time::timeout(READY_WAIT, available).await {
+            Ok(Ok(Some(ready))) => deployment = ready,
+            Ok(Ok(None)) => (),
+            Ok(Err(e)) => warn!("Failed waiting for deployment {}: {}", application_spec.name, e),
+            Err(_) => info!("Deployment {} not available after {:?}", application_spec.name, READY_WAIT),
+        }
+    }
+
+    if !exists {
+        publish(recorder, child_api.dry_run, Event {
+            type_: EventType::Normal,
+            reason: reasons::CREATING_DEPLOYMENT.into(),
+            note: Some(format!("Creating deployment `{}`", application_spec.name)),
+            action: "Reconciling".into(),
+            secondary: None,
+        })
+        .await?;
+    }
+
+    Ok(deployment)
+}
+
+/// Whether `FIELD_MANAGER` owns `spec.replicas` of a Deployment through server-side apply
+fn applies_replicas(deployment: &Deployment) -> bool {
+    deployment.metadata.managed_fields.iter().flatten()
+        .filter(|entry| entry.manager.as_deref() == Some(FIELD_MANAGER) && entry.operation.as_deref() == Some("Apply"))
+        .filter_map(|entry| entry.fields_v1.as_ref())
+        .any(|fields| fields.0.pointer("/f:spec/f:replicas").is_some())
+}
+
+/// Run `call` up to `PATCH_ATTEMPTS` times with exponential backoff while it fails with a retryable error
+async fn with_retries<T, F, Fut>(what: &str, mut call: F) -> Result<T, kube::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, kube::Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call().await {
+            Err(e) if attempt < PATCH_ATTEMPTS && is_retryable(&e) => {
+                let delay = PATCH_RETRY_DELAY * 2u32.pow(attempt - 1);
+                warn!("Retrying {} in {:?}: {}", what, delay, e);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Whether an apiserver error is transient and worth retrying
+///
+/// `ErrorResponse` does not carry the `Retry-After` header, so 429s use the same backoff
+fn is_retryable(error: &kube::Error) -> bool {
+    matches!(error, kube::Error::Api(response) if matches!(response.code, 429 | 500 | 503))
+}
+
+/// Whether the Deployment rolled out its current generation with `desired` available replicas
+fn is_deployment_available(desired: i32) -> impl Condition<Deployment> {
+    move |deployment: Option<&Deployment>| {
+        deployment.is_some_and(|d| {
+            let status = d.status.as_ref();
+            let observed = status.and_then(|s| s.observed_generation) >= d.metadata.generation;
+            let available = status.and_then(|s| s.available_replicas).unwrap_or(0);
+            let updated = status.and_then(|s| s.updated_replicas).unwrap_or(0);
+            observed && available >= desired && updated >= desired
+        })
+    }
+}
+
+/// Why the pods of an Application cannot become ready
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PodFailure {
+    /// Event and condition reason, `ImagePullFailed` or `CrashLoopBackOff`
+    pub reason: &'static str,
+    /// Message naming the failing container
+    pub message: String,
+}
+
+/// First image pull failure or crash looping container among the pods of an Application, if any
+pub async fn pod_failure(application_spec: &ApplicationSpec, ns: &str, child_api: &ChildApi<'_>) -> Result<Option<PodFailure>, kube::Error> {
+    let pods: Api<Pod> = Api::namespaced(child_api.client.clone(), ns);
+    let selector = labels(application_spec)
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(",");
+    count_call::<Pod>(child_api.calls, "list");
+    let pods = pods.list(&ListParams::default().labels(&selector)).await?;
 
-    let pp = PostParams::default();
-    match deployments.create(&pp, &deployment).await {
-        Ok(o) => {
-            let name = o.name_any();
-            assert_eq!(deployment.name_any(), name);
-            info!("Created deployment {}", application_spec.name)
+    Ok(failure_in(pods))
+}
+
+/// First container of `pods` waiting on an image pull or a crash loop back off
+fn failure_in(pods: impl IntoIterator<Item = Pod>) -> Option<PodFailure> {
+    pods.into_iter()
+        .filter_map(|pod| pod.status)
+        .flat_map(|status| {
+            let init = status.init_container_statuses.unwrap_or_default();
+            init.into_iter().chain(status.container_statuses.unwrap_or_default())
+        })
+        .find_map(|container| {
+            let reason = container.state.as_ref()
+                .and_then(|state| state.waiting.as_ref())
+                .and_then(|waiting| waiting.reason.as_deref());
+            match reason {
+                Some("ImagePullBackOff" | "ErrImagePull") => Some(PodFailure {
+                    reason: reasons::IMAGE_PULL_FAILED,
+                    message: format!("Failed to pull image `{}` for container `{}`", container.image, container.name),
+                }),
+                Some("CrashLoopBackOff") => Some(PodFailure {
+                    reason: reasons::CRASH_LOOP_BACK_OFF,
+                    message: format!("Container `{}` is crash looping after {} restarts", container.name, container.restart_count),
+                }),
+                _ => None,
+            }
+        })
+}
+
+/// Delete the Deployment of an Application if there is one, publishing an event on `recorder`
+#[instrument(skip_all, fields(app = %app.spec.name, namespace = %ns))]
+pub async fn cleanup_deployment(app: &Application, ns: &str, child_api: &ChildApi<'_>, recorder: &Recorder) -> Result<(), kube::Error> {
+    let deployments: Api<Deployment> = Api::namespaced(child_api.client.clone(), ns);
+
+    if !delete_child(&deployments, app, "deployment", child_api).await? {
+        return Ok(());
+    }
+
+    publish(recorder, child_api.dry_run, Event {
+        type_: EventType::Normal,
+        reason: reasons::DELETING_DEPLOYMENT.into(),
+        note: Some(format!("Deleting deployment `{}`", app.spec.name)),
+        action: "Reconciling".into(),
+        secondary: None,
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Desired Service of an Application with children in `ns`
+pub fn service_for(app: &Application, expose: &ExposeSpec, ns: &str) -> Service {
+    let application_spec = &app.spec;
+    let labels = labels(application_spec);
+    let resource_labels = resource_labels(application_spec);
+    let target_port = expose.target_port
+        .or(application_spec.port)
+        .unwrap_or(expose.port);
+    serde_json::from_value(json!({
+        "apiVersion": "v1",
+        "kind": "Service",
+        "metadata": {
+            "name": application_spec.name,
+            "ownerReferences": owner_references(app, ns),
+            "labels": resource_labels,
+            "annotations": resource_annotations(app)
         },
-        Err(kube::Error::Api(ae)) => assert_eq!(ae.code, 409),
-        Err(e) => return Err(e.into())
-    };
+        "spec": {
+            "clusterIP": expose.headless.then_some("None"),
+            "selector": labels,
+            "ports": [{
+                "port": expose.port,
+                "targetPort": target_port
+            }]
+        }
+    })).expect("Something is wrong with the service")
+}
+
+/// Server-side apply a ClusterIP Service selecting the pods of an Application
+pub async fn create_service(app: &Application, expose: &ExposeSpec, ns: &str, child_api: &ChildApi<'_>) -> Result<Service, kube::Error> {
+    let application_spec = &app.spec;
+    info!("Applying service for {}", application_spec.name);
+    let services: Api<Service> = Api::namespaced(child_api.client.clone(), ns);
+    let service = service_for(app, expose, ns);
+
+    // clusterIP is immutable, toggling `headless` needs a new Service
+    count_call::<Service>(child_api.calls, "get");
+    if let Some(existing) = services.get_opt(&application_spec.name).await? {
+        if is_headless(&existing) != expose.headless {
+            info!("Recreating service {} to toggle headless", application_spec.name);
+            delete_child(&services, app, "service", child_api).await?;
+        }
+    }
+
+    let ps = PatchParams { dry_run: child_api.dry_run, ..PatchParams::apply(FIELD_MANAGER).force() };
+    count_call::<Service>(child_api.calls, "patch");
+    throttle(child_api.limiter).await;
+    let service = services
+        .patch(&application_spec.name, &ps, &Patch::Apply(&service))
+        .await?;
+    info!("Applied service {}{}", application_spec.name, dry_run_suffix(child_api.dry_run));
+
+    Ok(service)
+}
+
+/// Whether a Service has no cluster IP
+fn is_headless(service: &Service) -> bool {
+    service.spec.as_ref().and_then(|spec| spec.cluster_ip.as_deref()) == Some("None")
+}
+
+pub async fn cleanup_service(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    let services: Api<Service> = Api::namespaced(child_api.client.clone(), ns);
+
+    delete_child(&services, app, "service", child_api).await?;
 
     Ok(())
 }
 
-pub async fn cleanup_deployment(application_spec: &ApplicationSpec, ns: &str, client: Client) -> Result<(), kube::Error> {
-    info!("Cleaning up deployment for {}", application_spec.name);
+/// Desired Ingress of an Application with children in `ns`
+pub fn ingress_for(app: &Application, ingress: &IngressSpec, expose: &ExposeSpec, ns: &str) -> Ingress {
+    let application_spec = &app.spec;
+    serde_json::from_value(json!({
+        "apiVersion": "networking.k8s.io/v1",
+        "kind": "Ingress",
+        "metadata": {
+            "name": application_spec.name,
+            "ownerReferences": owner_references(app, ns),
+            "labels": resource_labels(application_spec),
+            "annotations": resource_annotations(app)
+        },
+        "spec": {
+            "ingressClassName": ingress.ingress_class_name,
+            "rules": [{
+                "host": ingress.host,
+                "http": {
+                    "paths": [{
+                        "path": ingress.path.as_deref().unwrap_or("/"),
+                        "pathType": "Prefix",
+                        "backend": {
+                            "service": {
+                                "name": application_spec.name,
+                                "port": { "number": expose.port }
+                            }
+                        }
+                    }]
+                }
+            }]
+        }
+    })).expect("Something is wrong with the ingress")
+}
 
-    let deployments: Api<Deployment> = Api::namespaced(client, ns);
+/// Server-side apply an Ingress routing `ingress.host` and `ingress.path` to the Service of an Application
+pub async fn create_ingress(app: &Application, ingress: &IngressSpec, expose: &ExposeSpec, ns: &str, child_api: &ChildApi<'_>) -> Result<Ingress, kube::Error> {
+    let application_spec = &app.spec;
+    info!("Applying ingress for {}", application_spec.name);
+    let ingresses: Api<Ingress> = Api::namespaced(child_api.client.clone(), ns);
+    let ingress = ingress_for(app, ingress, expose, ns);
 
-    let dp = deployments.get_opt(&application_spec.name).await?;
-    if dp == None {
-        info!("No deployment active for {}", application_spec.name);
-        return Ok(());
-    };
+    let ps = PatchParams { dry_run: child_api.dry_run, ..PatchParams::apply(FIELD_MANAGER).force() };
+    count_call::<Ingress>(child_api.calls, "patch");
+    throttle(child_api.limiter).await;
+    let ingress = ingresses
+        .patch(&application_spec.name, &ps, &Patch::Apply(&ingress))
+        .await?;
+    info!("Applied ingress {}{}", application_spec.name, dry_run_suffix(child_api.dry_run));
 
-    deployments.delete(&application_spec.name, &DeleteParams::default()).await?
-        .map_left(|o| {
-            info!("Deleting deployment: {:?}", o.status);
-        })
-        .map_right(|s| info!("Deleted deployment: {:?}", s));
+    Ok(ingress)
+}
+
+pub async fn cleanup_ingress(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    let ingresses: Api<Ingress> = Api::namespaced(child_api.client.clone(), ns);
+
+    delete_child(&ingresses, app, "ingress", child_api).await?;
 
     Ok(())
 }
+
+/// Desired HorizontalPodAutoscaler of an Application with children in `ns`
+pub fn hpa_for(app: &Application, autoscaling: &AutoscalingSpec, ns: &str) -> HorizontalPodAutoscaler {
+    let application_spec = &app.spec;
+    serde_json::from_value(json!({
+        "apiVersion": "autoscaling/v2",
+        "kind": "HorizontalPodAutoscaler",
+        "metadata": {
+            "name": application_spec.name,
+            "ownerReferences": owner_references(app, ns),
+            "labels": resource_labels(application_spec),
+            "annotations": resource_annotations(app)
+        },
+        "spec": {
+            "scaleTargetRef": {
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "name": application_spec.name
+            },
+            "minReplicas": autoscaling.min_replicas,
+            "maxReplicas": autoscaling.max_replicas,
+            "metrics": [{
+                "type": "Resource",
+                "resource": {
+                    "name": "cpu",
+                    "target": {
+                        "type": "Utilization",
+                        "averageUtilization": autoscaling.target_cpu_utilization.unwrap_or(DEFAULT_TARGET_CPU_UTILIZATION)
+                    }
+                }
+            }]
+        }
+    })).expect("Something is wrong with the horizontal pod autoscaler")
+}
+
+/// Server-side apply a HorizontalPodAutoscaler scaling the Deployment of an Application
+pub async fn create_hpa(app: &Application, autoscaling: &AutoscalingSpec, ns: &str, child_api: &ChildApi<'_>) -> Result<HorizontalPodAutoscaler, kube::Error> {
+    let application_spec = &app.spec;
+    info!("Applying horizontal pod autoscaler for {}", application_spec.name);
+    let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(child_api.client.clone(), ns);
+    let hpa = hpa_for(app, autoscaling, ns);
+
+    let ps = PatchParams { dry_run: child_api.dry_run, ..PatchParams::apply(FIELD_MANAGER).force() };
+    count_call::<HorizontalPodAutoscaler>(child_api.calls, "patch");
+    throttle(child_api.limiter).await;
+    let hpa = hpas
+        .patch(&application_spec.name, &ps, &Patch::Apply(&hpa))
+        .await?;
+    info!("Applied horizontal pod autoscaler {}{}", application_spec.name, dry_run_suffix(child_api.dry_run));
+
+    Ok(hpa)
+}
+
+pub async fn cleanup_hpa(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    let hpas: Api<HorizontalPodAutoscaler> = Api::namespaced(child_api.client.clone(), ns);
+
+    delete_child(&hpas, app, "horizontal pod autoscaler", child_api).await?;
+
+    Ok(())
+}
+
+/// Desired PodDisruptionBudget of an Application with children in `ns`
+pub fn pdb_for(app: &Application, pdb: &PdbSpec, ns: &str) -> PodDisruptionBudget {
+    let application_spec = &app.spec;
+    serde_json::from_value(json!({
+        "apiVersion": "policy/v1",
+        "kind": "PodDisruptionBudget",
+        "metadata": {
+            "name": application_spec.name,
+            "ownerReferences": owner_references(app, ns),
+            "labels": resource_labels(application_spec),
+            "annotations": resource_annotations(app)
+        },
+        "spec": {
+            "selector": {
+                "matchLabels": labels(application_spec)
+            },
+            "minAvailable": pdb.min_available,
+            "maxUnavailable": pdb.max_unavailable
+        }
+    })).expect("Something is wrong with the pod disruption budget")
+}
+
+/// Server-side apply a PodDisruptionBudget selecting the pods of an Application
+pub async fn create_pdb(app: &Application, pdb: &PdbSpec, ns: &str, child_api: &ChildApi<'_>) -> Result<PodDisruptionBudget, kube::Error> {
+    let application_spec = &app.spec;
+    info!("Applying pod disruption budget for {}", application_spec.name);
+    let pdbs: Api<PodDisruptionBudget> = Api::namespaced(child_api.client.clone(), ns);
+    let budget = pdb_for(app, pdb, ns);
+
+    let ps = PatchParams { dry_run: child_api.dry_run, ..PatchParams::apply(FIELD_MANAGER).force() };
+    count_call::<PodDisruptionBudget>(child_api.calls, "patch");
+    throttle(child_api.limiter).await;
+    let budget = pdbs
+        .patch(&application_spec.name, &ps, &Patch::Apply(&budget))
+        .await?;
+    info!("Applied pod disruption budget {}{}", application_spec.name, dry_run_suffix(child_api.dry_run));
+
+    Ok(budget)
+}
+
+pub async fn cleanup_pdb(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    let pdbs: Api<PodDisruptionBudget> = Api::namespaced(child_api.client.clone(), ns);
+
+    delete_child(&pdbs, app, "pod disruption budget", child_api).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Application `demo` in namespace `apps` with `spec` merged over a minimal valid spec
+    fn app(spec: serde_json::Value) -> Application {
+        let mut base = json!({ "name": "demo", "image": "nginx:1.23", "deploy": true });
+        base.as_object_mut().unwrap().extend(spec.as_object().unwrap().clone());
+        serde_json::from_value(json!({
+            "apiVersion": "per.naess/v1alpha1",
+            "kind": "Application",
+            "metadata": { "name": "demo", "namespace": "apps", "uid": "5d5b0a2c-5c7e-4b8e-9a1e-0c6f2d8d7a11" },
+            "spec": base
+        }))
+        .unwrap()
+    }
+
+    /// Generated Deployment of `app` in its own namespace as JSON, for easy assertions
+    fn deployment(app: &Application) -> serde_json::Value {
+        serde_json::to_value(deployment_for(app, None, "apps")).unwrap()
+    }
+
+    fn container(deployment: &serde_json::Value) -> &serde_json::Value {
+        &deployment["spec"]["template"]["spec"]["containers"][0]
+    }
+
+    /// Pod whose `demo` container is waiting with `reason`
+    fn waiting_pod(reason: &str) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": "demo-6d4cf56db6-x2x8k" },
+            "status": {
+                "phase": "Pending",
+                "containerStatuses": [{
+                    "name": "demo",
+                    "image": "nginx:does-not-exist",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": 0,
+                    "state": { "waiting": { "reason": reason, "message": "Back-off pulling image" } }
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn failure_names_the_image() {
+        let expected = Some(PodFailure {
+            reason: reasons::IMAGE_PULL_FAILED,
+            message: "Failed to pull image `nginx:does-not-exist` for container `demo`".into(),
+        });
+        assert_eq!(failure_in(vec![waiting_pod("ImagePullBackOff")]), expected);
+        assert_eq!(failure_in(vec![waiting_pod("ContainerCreating"), waiting_pod("ErrImagePull")]), expected);
+        assert_eq!(failure_in(vec![waiting_pod("ContainerCreating")]), None);
+        assert_eq!(failure_in(Vec::new()), None);
+    }
+
+    #[test]
+    fn failure_detects_crash_loops() {
+        let mut pod = waiting_pod("CrashLoopBackOff");
+        pod.status.as_mut().unwrap().container_statuses.as_mut().unwrap()[0].restart_count = 4;
+        let expected = PodFailure {
+            reason: reasons::CRASH_LOOP_BACK_OFF,
+            message: "Container `demo` is crash looping after 4 restarts".into(),
+        };
+        assert_eq!(failure_in(vec![pod]), Some(expected));
+    }
+
+    #[test]
+    fn desired_replicas_prefers_override_annotation() {
+        assert_eq!(desired_replicas(&app(json!({}))), (Some(DEFAULT_REPLICAS), "Default"));
+        assert_eq!(desired_replicas(&app(json!({ "replicas": 3 }))), (Some(3), "Spec"));
+
+        let mut overridden = app(json!({ "replicas": 3 }));
+        overridden.annotations_mut().insert(REPLICAS_OVERRIDE_ANNOTATION.into(), "5".into());
+        assert_eq!(desired_replicas(&overridden), (Some(5), "Annotation"));
+
+        overridden.annotations_mut().insert(REPLICAS_OVERRIDE_ANNOTATION.into(), "-1".into());
+        assert_eq!(desired_replicas(&overridden), (Some(3), "Spec"));
+    }
+
+    #[test]
+    fn desired_replicas_leaves_autoscaled_replicas_alone() {
+        let app = app(json!({ "replicas": 3, "autoscaling": { "max_replicas": 5 } }));
+        assert_eq!(desired_replicas(&app), (None, "Autoscaling"));
+        assert!(deployment(&app)["spec"].get("replicas").is_none());
+    }
+
+    #[test]
+    fn spec_checksum_ignores_scaling() {
+        let checksum = spec_checksum(&app(json!({})).spec);
+        assert_eq!(checksum.len(), 64);
+        assert_eq!(spec_checksum(&app(json!({ "replicas": 4 })).spec), checksum);
+        assert_ne!(spec_checksum(&app(json!({ "image": "nginx:1.24" })).spec), checksum);
+    }
+
+    #[test]
+    fn roll_on_config_change_stamps_checksum() {
+        let plain = deployment(&app(json!({})));
+        assert!(plain["spec"]["template"]["metadata"]["annotations"].get(SPEC_CHECKSUM_ANNOTATION).is_none());
+
+        let app = app(json!({ "roll_on_config_change": true }));
+        let rolling = deployment(&app);
+        assert_eq!(rolling["spec"]["template"]["metadata"]["annotations"][SPEC_CHECKSUM_ANNOTATION], spec_checksum(&app.spec));
+    }
+
+    #[test]
+    fn deployment_selects_pods_by_application_name() {
+        let deployment = deployment(&app(json!({})));
+        let expected = json!({ "app.kubernetes.io/name": "demo", "app.kubernetes.io/managed-by": MANAGED_BY });
+        assert_eq!(deployment["spec"]["selector"]["matchLabels"], expected);
+        assert_eq!(deployment["spec"]["template"]["metadata"]["labels"], expected);
+        assert_eq!(deployment["metadata"]["labels"], expected);
+    }
+
+    #[test]
+    fn deployment_is_owned_by_application_in_same_namespace() {
+        let app = app(json!({}));
+        let owners = deployment_for(&app, None, "apps").metadata.owner_references.unwrap();
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].uid, "5d5b0a2c-5c7e-4b8e-9a1e-0c6f2d8d7a11");
+        assert_eq!(owners[0].controller, Some(true));
+
+        assert!(deployment_for(&app, None, "elsewhere").metadata.owner_references.is_none());
+    }
+
+    #[test]
+    fn children_name_their_application() {
+        let app = app(json!({ "expose": { "port": 80 }, "annotations": { "team": "web" } }));
+        let expose = app.spec.expose.clone().unwrap();
+        let annotations = deployment_for(&app, None, "elsewhere").metadata.annotations.unwrap();
+        assert_eq!(annotations.get(OWNER_ANNOTATION).map(String::as_str), Some("apps/demo"));
+        assert_eq!(annotations.get("team").map(String::as_str), Some("web"));
+        let service = service_for(&app, &expose, "elsewhere");
+        assert_eq!(service.annotations().get(OWNER_ANNOTATION).map(String::as_str), Some("apps/demo"));
+        let template = deployment(&app)["spec"]["template"]["metadata"]["annotations"].clone();
+        assert!(template.get(OWNER_ANNOTATION).is_none());
+    }
+
+    /// Deployment with `managedFields` entries of `(manager, operation, fields)`
+    fn managed_deployment(entries: &[(&str, &str, serde_json::Value)]) -> Deployment {
+        let managed_fields: Vec<_> = entries.iter()
+            .map(|(manager, operation, fields)| json!({ "manager": manager, "operation": operation, "fieldsType": "FieldsV1", "fieldsV1": fields }))
+            .collect();
+        serde_json::from_value(json!({ "metadata": { "name": "demo", "managedFields": managed_fields }, "spec": { "replicas": 4 } })).unwrap()
+    }
+
+    #[test]
+    fn replicas_are_kept_until_the_autoscaler_owns_them() {
+        let replicas = json!({ "f:spec": { "f:replicas": {}, "f:template": {} } });
+        let template = json!({ "f:spec": { "f:template": {} } });
+        // Applied before autoscaling was enabled
+        assert!(applies_replicas(&managed_deployment(&[(FIELD_MANAGER, "Apply", replicas.clone())])));
+        // The autoscaler scaled through the scale subresource and took replicas over
+        let scaled = managed_deployment(&[
+            (FIELD_MANAGER, "Apply", template.clone()),
+            ("kube-controller-manager", "Update", replicas.clone()),
+        ]);
+        assert!(!applies_replicas(&scaled));
+        assert!(!applies_replicas(&managed_deployment(&[("kubectl", "Apply", replicas)])));
+        assert!(!applies_replicas(&managed_deployment(&[])));
+    }
+
+    #[test]
+    fn hpa_scales_the_deployment() {
+        let app = app(json!({ "autoscaling": { "min_replicas": 2, "max_replicas": 5 } }));
+        let hpa = serde_json::to_value(hpa_for(&app, app.spec.autoscaling.as_ref().unwrap(), "apps")).unwrap();
+        assert_eq!(hpa["spec"]["scaleTargetRef"], json!({ "apiVersion": "apps/v1", "kind": "Deployment", "name": "demo" }));
+        assert_eq!(hpa["spec"]["minReplicas"], json!(2));
+        assert_eq!(hpa["spec"]["maxReplicas"], json!(5));
+        let target = &hpa["spec"]["metrics"][0]["resource"];
+        assert_eq!(target["name"], json!("cpu"));
+        assert_eq!(target["target"]["averageUtilization"], json!(DEFAULT_TARGET_CPU_UTILIZATION));
+        assert_eq!(hpa["metadata"]["ownerReferences"][0]["kind"], json!("Application"));
+        // The autoscaler owns replicas, the Deployment leaves them out
+        assert!(deployment(&app)["spec"].get("replicas").is_none());
+    }
+
+    /// Deployment at `generation` with `status`
+    fn rolled_out(generation: i64, status: serde_json::Value) -> Deployment {
+        serde_json::from_value(json!({ "metadata": { "name": "demo", "generation": generation }, "status": status })).unwrap()
+    }
+
+    /// Apiserver error response with `code`
+    fn api_error(code: u16) -> kube::Error {
+        kube::Error::Api(kube::error::ErrorResponse { status: "Failure".into(), message: "failed".into(), reason: String::new(), code })
+    }
+
+    #[test]
+    fn transient_errors_are_retryable() {
+        for code in [429, 500, 503] {
+            assert!(is_retryable(&api_error(code)), "{}", code);
+        }
+        for code in [400, 404, 409, 422] {
+            assert!(!is_retryable(&api_error(code)), "{}", code);
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_call_succeeds() {
+        let mut calls = 0;
+        let result = with_retries("test", || {
+            calls += 1;
+            let result = if calls == 1 { Err(api_error(503)) } else { Ok(calls) };
+            async move { result }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let mut calls = 0;
+        let result = with_retries("test", || {
+            calls += 1;
+            async { Err::<(), _>(api_error(404)) }
+        })
+        .await;
+        assert!(matches!(result, Err(kube::Error::Api(e)) if e.code == 404));
+        assert_eq!(calls, 1);
+
+        // Retryable errors give up after `PATCH_ATTEMPTS`
+        calls = 0;
+        let result = with_retries("test", || {
+            calls += 1;
+            async { Err::<(), _>(api_error(500)) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, PATCH_ATTEMPTS);
+    }
+
+    #[test]
+    fn deployment_is_available_once_its_generation_rolled_out() {
+        let available = is_deployment_available(3);
+        let lagging = rolled_out(2, json!({ "observedGeneration": 1, "availableReplicas": 3, "updatedReplicas": 3 }));
+        assert!(!available.matches_object(Some(&lagging)));
+        let partial = rolled_out(2, json!({ "observedGeneration": 2, "availableReplicas": 2, "updatedReplicas": 3 }));
+        assert!(!available.matches_object(Some(&partial)));
+        let outdated = rolled_out(2, json!({ "observedGeneration": 2, "availableReplicas": 3, "updatedReplicas": 1 }));
+        assert!(!available.matches_object(Some(&outdated)));
+        let full = rolled_out(2, json!({ "observedGeneration": 2, "availableReplicas": 3, "updatedReplicas": 3 }));
+        assert!(available.matches_object(Some(&full)));
+        assert!(!available.matches_object(None));
+    }
+
+    #[test]
+    fn ingress_routes_to_the_service_port() {
+        let routed = app(json!({
+            "expose": { "port": 8080 },
+            "ingress": { "host": "demo.example.com", "path": "/api", "ingress_class_name": "nginx" }
+        }));
+        let ingress = ingress_for(&routed, routed.spec.ingress.as_ref().unwrap(), routed.spec.expose.as_ref().unwrap(), "apps");
+        let ingress = serde_json::to_value(ingress).unwrap();
+        assert_eq!(ingress["spec"]["ingressClassName"], "nginx");
+        let rule = &ingress["spec"]["rules"][0];
+        assert_eq!(rule["host"], "demo.example.com");
+        let path = &rule["http"]["paths"][0];
+        assert_eq!(path["path"], "/api");
+        assert_eq!(path["pathType"], "Prefix");
+        assert_eq!(path["backend"]["service"], json!({ "name": "demo", "port": { "number": 8080 } }));
+        assert_eq!(ingress["metadata"]["ownerReferences"][0]["kind"], "Application");
+
+        let defaulted = app(json!({ "expose": { "port": 80 }, "ingress": {} }));
+        let ingress = ingress_for(&defaulted, defaulted.spec.ingress.as_ref().unwrap(), defaulted.spec.expose.as_ref().unwrap(), "apps");
+        let ingress = serde_json::to_value(ingress).unwrap();
+        let rule = &ingress["spec"]["rules"][0];
+        assert!(rule.get("host").is_none());
+        assert_eq!(rule["http"]["paths"][0]["path"], "/");
+        assert_eq!(rule["http"]["paths"][0]["backend"]["service"]["port"]["number"], 80);
+    }
+
+    #[test]
+    fn pdb_selects_the_application_pods() {
+        let app = app(json!({ "pdb": { "min_available": "50%" } }));
+        let pdb = serde_json::to_value(pdb_for(&app, app.spec.pdb.as_ref().unwrap(), "apps")).unwrap();
+        assert_eq!(pdb["spec"]["selector"]["matchLabels"], json!(labels(&app.spec)));
+        assert_eq!(pdb["spec"]["minAvailable"], "50%");
+        assert!(pdb["spec"].get("maxUnavailable").is_none());
+        let owner = &pdb["metadata"]["ownerReferences"][0];
+        assert_eq!(owner["kind"], "Application");
+        assert_eq!(owner["name"], "demo");
+        assert_eq!(owner["uid"], "5d5b0a2c-5c7e-4b8e-9a1e-0c6f2d8d7a11");
+        assert_eq!(owner["controller"], true);
+    }
+
+    #[test]
+    fn headless_service_has_no_cluster_ip() {
+        let headless = app(json!({ "expose": { "port": 80, "headless": true } }));
+        assert!(is_headless(&service_for(&headless, headless.spec.expose.as_ref().unwrap(), "apps")));
+
+        let cluster_ip = app(json!({ "expose": { "port": 80 } }));
+        let mut allocated = service_for(&cluster_ip, cluster_ip.spec.expose.as_ref().unwrap(), "apps");
+        assert!(!is_headless(&allocated));
+        allocated.spec.as_mut().unwrap().cluster_ip = Some("10.96.12.34".into());
+        assert!(!is_headless(&allocated));
+    }
+
+    #[test]
+    fn only_children_of_the_application_are_managed() {
+        let app = app(json!({ "target_namespace": "shared" }));
+        assert!(is_managed_by(&deployment_for(&app, None, "shared"), &app));
+        // Created before owner annotations, recognised by its owner reference
+        let mut referenced = deployment_for(&app, None, "apps");
+        referenced.metadata.annotations = None;
+        assert!(is_managed_by(&referenced, &app));
+
+        // An Application in another namespace targeting the same namespace and name
+        let mut other = app.clone();
+        other.metadata.namespace = Some("team-b".into());
+        other.metadata.uid = Some("0a9c3e55-2f4e-4c1b-8d7e-3b6a1f2e9c40".into());
+        assert!(!is_managed_by(&deployment_for(&other, None, "shared"), &app));
+        assert!(!is_managed_by(&deployment_for(&app, None, "shared"), &other));
+
+        // Objects the operator did not create, e.g. `target_namespace: kube-system` with `name: coredns`
+        let foreign: Deployment = serde_json::from_value(json!({
+            "metadata": { "name": "demo", "namespace": "shared", "labels": { "k8s-app": "kube-dns" } }
+        }))
+        .unwrap();
+        assert!(!is_managed_by(&foreign, &app));
+        let mut unlabelled = deployment_for(&app, None, "shared");
+        unlabelled.metadata.labels = None;
+        assert!(!is_managed_by(&unlabelled, &app));
+    }
+
+    #[test]
+    fn deployment_omits_unset_container_fields() {
+        let deployment = deployment(&app(json!({})));
+        let container = container(&deployment);
+        for key in ["ports", "command", "args", "livenessProbe", "readinessProbe", "resources", "volumeMounts"] {
+            assert!(container.get(key).is_none(), "{} should be omitted", key);
+        }
+        let pod = &deployment["spec"]["template"]["spec"];
+        for key in ["volumes", "initContainers", "nodeSelector", "tolerations", "affinity", "topologySpreadConstraints", "serviceAccountName", "automountServiceAccountToken"] {
+            assert!(pod.get(key).is_none(), "{} should be omitted", key);
+        }
+    }
+
+    #[test]
+    fn deployment_declares_container_port() {
+        let deployment = deployment(&app(json!({ "port": 8080 })));
+        assert_eq!(container(&deployment)["ports"], json!([{ "containerPort": 8080 }]));
+    }
+
+    #[test]
+    fn deployment_sets_probes() {
+        let probe = json!({ "httpGet": { "path": "/healthz", "port": 8080 }, "periodSeconds": 5 });
+        let deployment = deployment(&app(json!({ "liveness_probe": probe, "readiness_probe": probe })));
+        assert_eq!(container(&deployment)["livenessProbe"], probe);
+        assert_eq!(container(&deployment)["readinessProbe"], probe);
+    }
+
+    #[test]
+    fn deployment_overrides_command_and_args() {
+        let deployment = deployment(&app(json!({ "command": ["/bin/server"], "args": ["--verbose"] })));
+        assert_eq!(container(&deployment)["command"], json!(["/bin/server"]));
+        assert_eq!(container(&deployment)["args"], json!(["--verbose"]));
+    }
+
+    #[test]
+    fn deployment_sets_scheduling_constraints() {
+        let deployment = deployment(&app(json!({
+            "node_selector": { "disktype": "ssd" },
+            "tolerations": [{ "key": "dedicated", "operator": "Equal", "value": "apps", "effect": "NoSchedule" }],
+            "affinity": { "nodeAffinity": { "requiredDuringSchedulingIgnoredDuringExecution": {
+                "nodeSelectorTerms": [{ "matchExpressions": [{ "key": "zone", "operator": "In", "values": ["a"] }] }]
+            } } }
+        })));
+        let pod = &deployment["spec"]["template"]["spec"];
+        assert_eq!(pod["nodeSelector"], json!({ "disktype": "ssd" }));
+        assert_eq!(pod["tolerations"][0]["key"], "dedicated");
+        assert_eq!(pod["affinity"]["nodeAffinity"]["requiredDuringSchedulingIgnoredDuringExecution"]["nodeSelectorTerms"][0]["matchExpressions"][0]["key"], "zone");
+    }
+
+    #[test]
+    fn deployment_spreads_pods_across_zones() {
+        let constraint = json!({
+            "maxSkew": 1,
+            "topologyKey": "topology.kubernetes.io/zone",
+            "whenUnsatisfiable": "DoNotSchedule",
+            "labelSelector": { "matchLabels": { "app.kubernetes.io/name": "demo" } }
+        });
+        let deployment = deployment(&app(json!({ "topology_spread_constraints": [constraint] })));
+        assert_eq!(deployment["spec"]["template"]["spec"]["topologySpreadConstraints"], json!([constraint]));
+    }
+
+    #[test]
+    fn managed_labels_win_over_user_labels() {
+        let deployment = deployment(&app(json!({
+            "labels": { "team": "web", "app.kubernetes.io/name": "other" },
+            "annotations": { "owner": "web-team" }
+        })));
+        let labels = &deployment["metadata"]["labels"];
+        assert_eq!(labels["team"], "web");
+        assert_eq!(labels["app.kubernetes.io/name"], "demo");
+        assert_eq!(deployment["spec"]["template"]["metadata"]["labels"]["team"], "web");
+        assert_eq!(deployment["metadata"]["annotations"]["owner"], "web-team");
+        assert_eq!(deployment["spec"]["template"]["metadata"]["annotations"]["owner"], "web-team");
+    }
+
+    #[test]
+    fn deployment_and_pod_labels_are_separate() {
+        let deployment = deployment(&app(json!({
+            "deployment_labels": { "cost-center": "42" },
+            "pod_labels": { "sidecar.istio.io/inject": "true" }
+        })));
+        assert_eq!(deployment["metadata"]["labels"]["cost-center"], "42");
+        assert!(deployment["metadata"]["labels"].get("sidecar.istio.io/inject").is_none());
+        let pod_labels = &deployment["spec"]["template"]["metadata"]["labels"];
+        assert_eq!(pod_labels["sidecar.istio.io/inject"], "true");
+        assert!(pod_labels.get("cost-center").is_none());
+        assert_eq!(pod_labels["app.kubernetes.io/name"], "demo");
+    }
+
+    #[test]
+    fn deployment_mounts_config_map() {
+        let deployment = deployment(&app(json!({ "config_mount": { "config_map": "demo-config", "mount_path": "/etc/demo" } })));
+        assert_eq!(deployment["spec"]["template"]["spec"]["volumes"], json!([{ "name": CONFIG_VOLUME, "configMap": { "name": "demo-config" } }]));
+        assert_eq!(container(&deployment)["volumeMounts"], json!([{ "name": CONFIG_VOLUME, "mountPath": "/etc/demo", "readOnly": true }]));
+    }
+
+    #[test]
+    fn deployment_mounts_secret_volume() {
+        let deployment = deployment(&app(json!({
+            "config_mount": { "config_map": "demo-config", "mount_path": "/etc/demo" },
+            "volumes": [{ "name": "tls", "secret": { "secretName": "demo-tls" } }],
+            "volume_mounts": [{ "name": "tls", "mountPath": "/etc/tls", "readOnly": true }]
+        })));
+        let volumes = &deployment["spec"]["template"]["spec"]["volumes"];
+        assert_eq!(volumes[0]["name"], CONFIG_VOLUME);
+        assert_eq!(volumes[1], json!({ "name": "tls", "secret": { "secretName": "demo-tls" } }));
+        assert_eq!(container(&deployment)["volumeMounts"][1], json!({ "name": "tls", "mountPath": "/etc/tls", "readOnly": true }));
+    }
+
+    #[test]
+    fn deployment_sets_rollout_settings() {
+        let recreate = deployment(&app(json!({
+            "strategy": { "type": "Recreate" },
+            "revision_history_limit": 3,
+            "progress_deadline_seconds": 120
+        })));
+        assert_eq!(recreate["spec"]["strategy"], json!({ "type": "Recreate" }));
+        assert_eq!(recreate["spec"]["revisionHistoryLimit"], 3);
+        assert_eq!(recreate["spec"]["progressDeadlineSeconds"], 120);
+
+        for rolling_update in [json!({ "maxSurge": 1, "maxUnavailable": "25%" }), json!({ "maxSurge": "50%", "maxUnavailable": 0 })] {
+            let strategy = json!({ "type": "RollingUpdate", "rollingUpdate": rolling_update });
+            let rolling = deployment(&app(json!({ "strategy": strategy })));
+            assert_eq!(rolling["spec"]["strategy"], strategy);
+        }
+    }
+
+    #[test]
+    fn deployment_sets_pod_settings() {
+        let deployment = deployment(&app(json!({
+            "service_account": "demo-sa",
+            "automount_service_account_token": false,
+            "termination_grace_period_seconds": 60
+        })));
+        let pod = &deployment["spec"]["template"]["spec"];
+        assert_eq!(pod["serviceAccountName"], "demo-sa");
+        assert_eq!(pod["automountServiceAccountToken"], false);
+        assert_eq!(pod["terminationGracePeriodSeconds"], 60);
+    }
+
+    #[test]
+    fn deployment_adds_sidecars_and_init_containers() {
+        let deployment = deployment(&app(json!({
+            "name": "My_App",
+            "sidecars": [{ "name": "proxy", "image": "envoyproxy/envoy:v1.24.0", "ports": [9901] }],
+            "init_containers": [{ "name": "migrate", "image": "demo/migrate:1", "command": ["migrate"] }]
+        })));
+        let pod = &deployment["spec"]["template"]["spec"];
+        assert_eq!(pod["containers"][0]["name"], "my-app");
+        assert_eq!(pod["containers"][1], json!({ "name": "proxy", "image": "envoyproxy/envoy:v1.24.0", "ports": [{ "containerPort": 9901 }] }));
+        assert_eq!(pod["initContainers"], json!([{ "name": "migrate", "image": "demo/migrate:1", "command": ["migrate"] }]));
+    }
+}
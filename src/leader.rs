@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use k8s_openapi::{
+    api::coordination::v1::{Lease, LeaseSpec},
+    apimachinery::pkg::apis::meta::v1::MicroTime,
+    chrono::{self, Utc},
+};
+use kube::{api::{ObjectMeta, PostParams}, Api, Client};
+use tokio::time::{sleep, Instant};
+use tracing::{info, warn};
+
+/// Name of the Lease replicas compete for
+pub const LEASE_NAME: &str = "customapps-operator-leader";
+
+/// Elects a single active operator replica through a `coordination.k8s.io` Lease
+pub struct LeaderElector {
+    leases: Api<Lease>,
+    identity: String,
+    lease_duration: Duration,
+}
+
+impl LeaderElector {
+    /// Lease lives in `POD_NAMESPACE`, or the client's default namespace when unset
+    pub fn new(client: Client, identity: String, lease_duration: Duration) -> Self {
+        let leases = match std::env::var("POD_NAMESPACE") {
+            Ok(ns) => Api::namespaced(client, &ns),
+            Err(_) => Api::default_namespaced(client),
+        };
+        Self { leases, identity, lease_duration }
+    }
+
+    /// Namespace the Lease lives in
+    pub fn namespace(&self) -> &str {
+        // resource url is `/apis/coordination.k8s.io/v1/namespaces/{ns}/leases`
+        self.leases
+            .resource_url()
+            .split('/')
+            .skip_while(|segment| *segment != "namespaces")
+            .nth(1)
+            .unwrap_or_default()
+    }
+
+    /// Take the lease when it is free or expired, or renew it when already held
+    ///
+    /// Returns whether this replica holds the lease afterwards
+    pub async fn try_acquire_or_renew(&self) -> Result<bool, kube::Error> {
+        let now = Utc::now();
+        let lease = match self.leases.get_opt(LEASE_NAME).await? {
+            Some(lease) => lease,
+            None => {
+                let lease = Lease {
+                    metadata: ObjectMeta { name: Some(LEASE_NAME.into()), ..ObjectMeta::default() },
+                    spec: Some(self.lease_spec(Some(MicroTime(now)), 0)),
+                };
+                return match self.leases.create(&PostParams::default(), &lease).await {
+                    Ok(_) => Ok(true),
+                    Err(kube::Error::Api(ae)) if ae.code == 409 => Ok(false),
+                    Err(e) => Err(e),
+                };
+            }
+        };
+
+        let spec = lease.spec.clone().unwrap_or_default();
+        let held = spec.holder_identity.as_deref() == Some(self.identity.as_str());
+        let expired = spec.renew_time.as_ref().is_none_or(|renewed| {
+            let duration = spec.lease_duration_seconds.unwrap_or_default();
+            renewed.0 + chrono::Duration::seconds(duration.into()) < now
+        });
+        if !held && !expired {
+            return Ok(false);
+        }
+
+        let (acquire_time, transitions) = if held {
+            (spec.acquire_time, spec.lease_transitions.unwrap_or_default())
+        } else {
+            (Some(MicroTime(now)), spec.lease_transitions.unwrap_or_default() + 1)
+        };
+        let lease = Lease { spec: Some(self.lease_spec(acquire_time, transitions)), ..lease };
+
+        // Replace carries the resourceVersion, so a competing replica's update makes this conflict
+        match self.leases.replace(LEASE_NAME, &PostParams::default(), &lease).await {
+            Ok(_) => Ok(true),
+            Err(kube::Error::Api(ae)) if ae.code == 409 => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Wait until this replica becomes leader
+    pub async fn acquire(&self) {
+        loop {
+            match self.try_acquire_or_renew().await {
+                Ok(true) => {
+                    info!("Acquired leadership as {}", self.identity);
+                    return;
+                },
+                Ok(false) => (),
+                Err(e) => warn!("Failed to acquire lease: {}", e),
+            }
+            sleep(self.retry_period()).await;
+        }
+    }
+
+    /// Keep renewing the lease, returning once leadership is lost
+    pub async fn hold(&self) {
+        let mut last_renew = Instant::now();
+        loop {
+            sleep(self.retry_period()).await;
+            match self.try_acquire_or_renew().await {
+                Ok(true) => last_renew = Instant::now(),
+                Ok(false) => {
+                    warn!("Lease taken over by another replica");
+                    return;
+                },
+                Err(e) if last_renew.elapsed() < self.lease_duration => {
+                    warn!("Failed to renew lease: {}", e);
+                },
+                Err(e) => {
+                    warn!("Failed to renew lease before it expired: {}", e);
+                    return;
+                },
+            }
+        }
+    }
+
+    fn retry_period(&self) -> Duration {
+        self.lease_duration / 3
+    }
+
+    fn lease_spec(&self, acquire_time: Option<MicroTime>, lease_transitions: i32) -> LeaseSpec {
+        LeaseSpec {
+            holder_identity: Some(self.identity.clone()),
+            lease_duration_seconds: Some(self.lease_duration.as_secs() as i32),
+            acquire_time,
+            renew_time: Some(MicroTime(Utc::now())),
+            lease_transitions: Some(lease_transitions),
+        }
+    }
+}
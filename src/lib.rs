@@ -6,10 +6,40 @@ pub enum Error {
     FinalizerError(#[source] kube::runtime::finalizer::Error<kube::Error>),
 
     #[error("SerializationError: {0}")]
-    SerializationError(#[source] serde_json::Error)
+    SerializationError(#[source] serde_json::Error),
+
+    #[error("Application has no namespace")]
+    MissingNamespace,
+
+    #[error("Kube error: {0}")]
+    KubeError(#[source] kube::Error),
+
+    #[error("Missing RBAC permissions: {0}")]
+    MissingPermissions(String),
+
+    #[error("Application CRD is not installed, please run: cargo run --bin crdgen | kubectl apply -f - ({0})")]
+    CrdNotInstalled(#[source] kube::Error),
+
+    #[error("Reconcile timed out after {0:?}")]
+    Timeout(std::time::Duration),
 }
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Short label describing the kind of an `Error`, used in metrics
+pub fn error_kind(error: &Error) -> &'static str {
+    use kube::runtime::finalizer::Error as FinalizerError;
+    match error {
+        Error::FinalizerError(FinalizerError::ApplyFailed(_) | FinalizerError::CleanupFailed(_)) => "api",
+        Error::FinalizerError(_) => "finalizer",
+        Error::SerializationError(_) => "serialization",
+        Error::MissingNamespace => "missing_namespace",
+        Error::KubeError(_) => "api",
+        Error::MissingPermissions(_) => "permissions",
+        Error::CrdNotInstalled(_) => "crd",
+        Error::Timeout(_) => "timeout",
+    }
+}
+
 /// State machinery for kubernetes, exposable to actix
 pub mod operator;
 pub use operator::Operator;
@@ -20,5 +50,34 @@ pub use operator::Application;
 /// Deployments
 pub mod deployment;
 
+/// Leader election between operator replicas
+pub mod leader;
+
+/// Startup RBAC self checks
+pub mod rbac;
+
 /// Log and trace integrations
 pub mod telemetry;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::runtime::finalizer::Error as FinalizerError;
+
+    #[test]
+    fn error_kinds() {
+        assert_eq!(error_kind(&Error::MissingNamespace), "missing_namespace");
+        assert_eq!(error_kind(&Error::MissingPermissions("patch deployments".into())), "permissions");
+        assert_eq!(error_kind(&Error::Timeout(std::time::Duration::from_secs(60))), "timeout");
+        let serde_error = serde_json::from_str::<u32>("x").unwrap_err();
+        assert_eq!(error_kind(&Error::SerializationError(serde_error)), "serialization");
+        assert_eq!(error_kind(&Error::FinalizerError(FinalizerError::UnnamedObject)), "finalizer");
+        let api_error = kube::Error::Api(kube::core::ErrorResponse {
+            status: "Failure".into(),
+            message: "boom".into(),
+            reason: "InternalError".into(),
+            code: 500,
+        });
+        assert_eq!(error_kind(&Error::FinalizerError(FinalizerError::ApplyFailed(api_error))), "api");
+    }
+}
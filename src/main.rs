@@ -1,36 +1,226 @@
-use kube::runtime::wait::Error;
 pub use operator::operator::*;
-use actix_web::{HttpRequest, Responder, HttpResponse, get, HttpServer, App, web::Data, middleware};
+use clap::{Parser, ValueEnum};
+use actix_web::{HttpRequest, Responder, HttpResponse, get, post, HttpServer, App, web::{self, Data}, middleware};
+use kube::core::{admission::{AdmissionRequest, AdmissionResponse, AdmissionReview}, DynamicObject};
 use prometheus::{TextEncoder, Encoder};
-use tracing::{info, warn};
+use serde::Deserialize;
+use rustls_pemfile::Item;
+use std::{fs::File, io::BufReader, time::Duration};
+use tracing::{error, info, warn};
 use tracing_subscriber::{prelude::*, EnvFilter, Registry};
 
+#[derive(Deserialize)]
+struct MetricsQuery {
+    /// Only return series labelled with this namespace
+    namespace: Option<String>,
+}
+
 #[get("/metrics")]
-async fn metrics(c: Data<Operator>, _req: HttpRequest) -> impl Responder {
-    let metrics = c.metrics();
+async fn metrics(c: Data<Operator>, query: web::Query<MetricsQuery>) -> impl Responder {
+    let mut metrics = c.metrics().await;
+    if let Some(namespace) = &query.namespace {
+        for family in metrics.iter_mut() {
+            let series = family
+                .take_metric()
+                .into_iter()
+                .filter(|m| m.get_label().iter().any(|l| l.get_name() == "namespace" && l.get_value() == namespace))
+                .collect();
+            family.set_metric(series);
+        }
+        metrics.retain(|family| !family.get_metric().is_empty());
+    }
     let encoder = TextEncoder::new();
     let mut buffer = vec![];
-    encoder.encode(&metrics, &mut buffer).unwrap();
-    HttpResponse::Ok().body(buffer)
+    match encoder.encode(&metrics, &mut buffer) {
+        Ok(()) => HttpResponse::Ok().content_type(encoder.format_type()).body(buffer),
+        Err(e) => {
+            error!("Failed to encode metrics: {}", e);
+            HttpResponse::InternalServerError().body(format!("Failed to encode metrics: {}", e))
+        },
+    }
 }
 
-#[get("/health")]
+/// Liveness, served on `/health` and the Kubernetes style `/healthz` and `/livez`
 async fn health(_: HttpRequest) -> impl Responder {
     HttpResponse::Ok().json("healthy")
 }
 
+#[get("/ready")]
+async fn ready(c: Data<Operator>, _req: HttpRequest) -> impl Responder {
+    if c.ready() {
+        HttpResponse::Ok().json("ready")
+    } else {
+        HttpResponse::ServiceUnavailable().json("not ready")
+    }
+}
+
+/// Settings the admission webhook validates Applications with
+#[derive(Clone)]
+struct WebhookConfig {
+    /// Image used for Applications that do not set `image`
+    default_image: Option<String>,
+}
+
+/// Validating admission webhook rejecting Applications that would fail to reconcile
+#[post("/validate")]
+async fn validate(config: Data<WebhookConfig>, review: web::Json<AdmissionReview<DynamicObject>>) -> impl Responder {
+    let request: AdmissionRequest<DynamicObject> = match review.into_inner().try_into() {
+        Ok(request) => request,
+        Err(e) => {
+            warn!("Invalid admission review: {}", e);
+            return HttpResponse::BadRequest().json(AdmissionResponse::invalid(e.to_string()).into_review());
+        },
+    };
+
+    let mut response = AdmissionResponse::from(&request);
+    if let Some(object) = &request.object {
+        let validation = serde_json::to_value(object)
+            .and_then(serde_json::from_value::<Application>)
+            .map_err(|e| format!("Invalid Application: {}", e))
+            .and_then(|app| app.spec.validate(config.default_image.as_deref()));
+        if let Err(message) = validation {
+            info!("Denying {:?} of Application {}: {}", request.operation, request.name, message);
+            response = response.deny(message);
+        }
+    }
+    HttpResponse::Ok().json(response.into_review())
+}
+
 #[get("/")]
 async fn index(c: Data<Operator>, _req: HttpRequest) -> impl Responder {
     let d = c.diagnostics().await;
     HttpResponse::Ok().json(&d)
 }
 
+/// Rustls config from `TLS_CERT_FILE` and `TLS_KEY_FILE`, `None` when neither is set
+fn tls_config() -> Result<Option<rustls::ServerConfig>, Box<dyn std::error::Error>> {
+    let (cert_file, key_file) = match (std::env::var("TLS_CERT_FILE"), std::env::var("TLS_KEY_FILE")) {
+        (Ok(cert_file), Ok(key_file)) => (cert_file, key_file),
+        (Err(_), Err(_)) => return Ok(None),
+        _ => return Err("TLS_CERT_FILE and TLS_KEY_FILE must be set together".into()),
+    };
+
+    let mut certs = BufReader::new(File::open(&cert_file).map_err(|e| format!("Failed to open {}: {}", cert_file, e))?);
+    let certs = rustls_pemfile::certs(&mut certs)?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect();
+
+    let mut keys = BufReader::new(File::open(&key_file).map_err(|e| format!("Failed to open {}: {}", key_file, e))?);
+    let key = rustls_pemfile::read_all(&mut keys)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| format!("No private key found in {}", key_file))?;
+
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(Some(config))
+}
+
+/// Log reconcile totals and uptime before the process exits
+async fn log_shutdown_summary(operator: &Operator) {
+    let diagnostics = operator.diagnostics().await;
+    let failures: f64 = operator
+        .metrics()
+        .await
+        .iter()
+        .filter(|family| family.get_name() == "app_controller_reconciliation_errors_total")
+        .flat_map(|family| family.get_metric())
+        .map(|m| m.get_counter().get_value())
+        .sum();
+    let uptime = chrono::Utc::now().signed_duration_since(diagnostics.started_at);
+    info!(
+        reconciles = diagnostics.reconcile_count,
+        failures = failures as u64,
+        uptime_secs = uptime.num_seconds(),
+        last_error = diagnostics.last_error.as_deref(),
+        "Shutting down"
+    );
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum LogFormat {
+    /// Human readable logs
+    Pretty,
+    /// Structured JSON logs
+    Json,
+}
+
+/// Kubernetes operator managing Applications
+///
+/// Every flag falls back to an environment variable, see the README for the remaining settings
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Address of the metrics and health web server
+    #[arg(long, env = "OPERATOR_BIND_ADDR", default_value = "0.0.0.0:8080")]
+    bind_addr: String,
+    /// Comma separated namespaces to watch, all namespaces when unset
+    #[arg(long, env = "WATCH_NAMESPACE", value_delimiter = ',')]
+    watch_namespace: Vec<String>,
+    /// Seconds between periodic reconciles of an Application [env: RECONCILE_INTERVAL_SECS] [default: 300]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    reconcile_interval_secs: Option<u64>,
+    /// Log output format [env: LOG_FORMAT] [default: pretty]
+    #[arg(long, value_enum, ignore_case = true)]
+    log_format: Option<LogFormat>,
+    /// Image used for Applications that do not set `image`
+    #[arg(long, env = "DEFAULT_IMAGE")]
+    default_image: Option<String>,
+}
+
+// The environment fallbacks below warn and use the default rather than refusing to start,
+// a typo in a deployment manifest should not crash loop the operator
+
+/// Log format from the flag, `LOG_FORMAT` in any case, or pretty
+///
+/// Returns the warning to log once tracing is set up when `LOG_FORMAT` is invalid
+fn log_format(flag: Option<LogFormat>) -> (LogFormat, Option<String>) {
+    match (flag, std::env::var("LOG_FORMAT")) {
+        (Some(format), _) => (format, None),
+        (None, Ok(format)) => match LogFormat::from_str(&format, true) {
+            Ok(format) => (format, None),
+            Err(_) => (LogFormat::Pretty, Some(format!("Invalid LOG_FORMAT `{}`, using pretty", format))),
+        },
+        (None, Err(_)) => (LogFormat::Pretty, None),
+    }
+}
+
+/// Interval between periodic reconciles from the flag, `RECONCILE_INTERVAL_SECS`, or 300 seconds
+///
+/// Zero is rejected as it would requeue every Application immediately, forever
+fn reconcile_interval(flag: Option<u64>) -> Duration {
+    let default = Duration::from_secs(300);
+    match (flag, std::env::var("RECONCILE_INTERVAL_SECS")) {
+        (Some(secs), _) => Duration::from_secs(secs),
+        (None, Ok(secs)) => match secs.parse::<u64>() {
+            Ok(parsed) if parsed > 0 => Duration::from_secs(parsed),
+            _ => {
+                warn!("Invalid RECONCILE_INTERVAL_SECS `{}`, using {:?}", secs, default);
+                default
+            },
+        },
+        (None, Err(_)) => default,
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
     // Setup tracing layers
-    #[cfg(feature = "telemtry")]
-    let telemetry = tracing_opentelemetry::layer().with_tracer(telemetry::init_tracer().await);
-    let logger = tracing_subscriber::fmt::layer();
+    #[cfg(feature = "telemetry")]
+    let telemetry = tracing_opentelemetry::layer().with_tracer(operator::telemetry::init_tracer().await?);
+    let (log_format, log_format_warning) = log_format(cli.log_format);
+    let logger = match log_format {
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().boxed(),
+    };
     let env_filter = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("info"))
         .unwrap();
@@ -42,33 +232,132 @@ async fn main() -> Result<(), Error> {
 
     // Decide on layers
     #[cfg(feature = "telemetry")]
-    let Collector =Registry::default().with(telemetry).with(logger).with(env_filter);
+    let collector = Registry::default().with(telemetry).with(logger).with(env_filter);
     #[cfg(not(feature = "telemetry"))]
     let collector = Registry::default().with(logger).with(env_filter);
 
     // Initialize tracing
     tracing::subscriber::set_global_default(collector).unwrap();
 
+    if let Some(warning) = log_format_warning {
+        warn!("{}", warning);
+    }
+    let requeue_interval = reconcile_interval(cli.reconcile_interval_secs);
+    let default_image = cli.default_image.filter(|image| !image.is_empty());
+    info!(bind_addr = %cli.bind_addr, watch_namespace = ?cli.watch_namespace, ?requeue_interval, ?log_format, ?default_image, "Starting");
+
     // Start kubernetes controller
-    let (operator, controller) = Operator::new().await;
+    let (operator, controller) = Operator::new(cli.watch_namespace, requeue_interval, default_image.clone()).await?;
 
-    // Start web server
+    // Only run the controller when SERVE_HTTP=false
+    if std::env::var("SERVE_HTTP").is_ok_and(|v| v == "false") {
+        info!("SERVE_HTTP=false, running controller without web server");
+        controller.await;
+        warn!("controller exited");
+        log_shutdown_summary(&operator).await;
+        return Ok(());
+    }
+
+    // Start web server, serving TLS when a certificate is configured
+    let bind_addr = cli.bind_addr;
+    let tls = tls_config()?;
+    let tls_enabled = tls.is_some();
+    // The apiserver only calls webhooks over HTTPS
+    let webhook = std::env::var("ENABLE_WEBHOOK").is_ok_and(|v| v == "true");
+    if webhook && !tls_enabled {
+        return Err("ENABLE_WEBHOOK requires TLS_CERT_FILE and TLS_KEY_FILE".into());
+    }
+    if webhook {
+        info!("Serving the validating webhook on /validate");
+    }
+    let app_operator = operator.clone();
+    let webhook_config = WebhookConfig { default_image };
     let server = HttpServer::new(move || {
         App::new()
-            .app_data(Data::new(operator.clone()))
-            .wrap(middleware::Logger::default().exclude("/health"))
+            .app_data(Data::new(app_operator.clone()))
+            .app_data(Data::new(webhook_config.clone()))
+            .wrap(
+                middleware::Logger::default()
+                    .exclude("/health")
+                    .exclude("/healthz")
+                    .exclude("/livez")
+                    .exclude("/ready"),
+            )
             .service(index)
-            .service(health)
+            .service(web::resource(["/health", "/healthz", "/livez"]).route(web::get().to(health)))
+            .service(ready)
             .service(metrics)
-    })
-    .bind("0.0.0.0:8080")
-    .expect("Can not bind to 0.0.0.0:8080")
+            .configure(|cfg| {
+                if webhook {
+                    cfg.service(validate);
+                }
+            })
+    });
+    let server = match tls {
+        Some(config) => server.bind_rustls(&bind_addr, config)?,
+        None => server.bind(&bind_addr)?,
+    }
     .shutdown_timeout(5);
+    info!("Web server listening on {:?} ({})", server.addrs(), if tls_enabled { "https" } else { "http" });
 
     tokio::select! {
         _ = controller => warn!("controller exited"),
         _ = server.run() => info!("actix exited"),
     }
+    log_shutdown_summary(&operator).await;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+    use serde_json::json;
+
+    /// AdmissionReview of creating Application `demo` running `image`
+    fn review(image: &str) -> serde_json::Value {
+        json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "0df28fbd-5f5f-11e8-bc74-36e6bb280816",
+                "kind": { "group": "per.naess", "version": "v1alpha1", "kind": "Application" },
+                "resource": { "group": "per.naess", "version": "v1alpha1", "resource": "applications" },
+                "name": "demo",
+                "namespace": "apps",
+                "operation": "CREATE",
+                "userInfo": { "username": "admin" },
+                "object": {
+                    "apiVersion": "per.naess/v1alpha1",
+                    "kind": "Application",
+                    "metadata": { "name": "demo", "namespace": "apps" },
+                    "spec": { "name": "demo", "image": image, "deploy": true }
+                },
+                "dryRun": false
+            }
+        })
+    }
+
+    /// `response.allowed` of the webhook for an Application running `image`
+    async fn allowed(image: &str) -> bool {
+        let webhook = App::new()
+            .app_data(Data::new(WebhookConfig { default_image: None }))
+            .service(validate);
+        let webhook = test::init_service(webhook).await;
+        let request = test::TestRequest::post().uri("/validate").set_json(review(image)).to_request();
+        let response: serde_json::Value = test::call_and_read_body_json(&webhook, request).await;
+        assert_eq!(response["response"]["uid"], "0df28fbd-5f5f-11e8-bc74-36e6bb280816");
+        response["response"]["allowed"].as_bool().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn validate_allows_valid_applications() {
+        assert!(allowed("nginx:1.23").await);
+    }
+
+    #[actix_web::test]
+    async fn validate_denies_invalid_images() {
+        assert!(!allowed("nginx:").await);
+    }
+}
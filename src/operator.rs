@@ -1,50 +1,506 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt::Debug, num::NonZeroU32, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}}, time::Duration};
 
 use chrono::DateTime;
-use futures::{future::BoxFuture, FutureExt, StreamExt};
-use k8s_openapi::{chrono::Utc, api::apps::v1::Deployment};
+use futures::{future::BoxFuture, Future, FutureExt, StreamExt};
+use governor::{Quota, RateLimiter};
+use k8s_openapi::{chrono::Utc, apimachinery::pkg::{apis::meta::v1::{Condition, Time}, util::intstr::IntOrString}, api::{apps::v1::{Deployment, DeploymentStatus, DeploymentStrategy}, core::v1::{Affinity, EnvVar, PodSecurityContext, Probe, ResourceRequirements, SecurityContext, Toleration, TopologySpreadConstraint, Volume, VolumeMount}}};
 use kube::{
     CustomResource, Client, 
     runtime::{
         events::{Recorder, Reporter, EventType, Event},
-        controller::Action, finalizer, Controller, 
+        controller::{self, Action}, finalizer, reflector::{self, reflector, Store}, watcher::{self, watcher}, Controller, WatchStreamExt,
     }, 
     ResourceExt, Api, Resource, api::{Patch, PatchParams, ListParams}
 };
-use prometheus::{IntCounter, HistogramVec, register_histogram_vec, register_int_counter, proto::MetricFamily, default_registry};
-use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use tokio::{sync::RwLock, time::Instant};
+use prometheus::{Gauge, IntCounterVec, IntGauge, IntGaugeVec, HistogramVec, Registry, register_gauge_with_registry, register_histogram_vec_with_registry, register_int_counter_vec_with_registry, register_int_gauge_with_registry, register_int_gauge_vec_with_registry, proto::MetricFamily};
+use rand::Rng;
+use schemars::{JsonSchema, gen::SchemaGenerator, schema::Schema};
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use tokio::{sync::{watch, RwLock, Semaphore}, time::Instant};
 use tracing::{instrument, info, warn, Span, field};
 
-use crate::{Error, telemetry, deployment::{create_deployment, cleanup_deployment}};
+use crate::{Error, error_kind, telemetry, leader::LeaderElector, rbac::{check_child_permissions, check_lease_permissions, check_permissions}, deployment::{ApiRateLimiter, ChildApi, count_call, publish, CONFIG_VOLUME, DEFAULT_REPLICAS, FIELD_MANAGER, MANAGED_BY, desired_replicas, foreign_children, labels, pod_failure, PodFailure, create_deployment, cleanup_deployment, create_service, cleanup_service, create_ingress, cleanup_ingress, create_hpa, cleanup_hpa, create_pdb, cleanup_pdb}};
 
-static CUSTOM_APP_FINALIZER: &str = "customapps.per.naess";
+/// Finalizer used unless overridden with `FINALIZER_NAME`
+pub static CUSTOM_APP_FINALIZER: &str = "customapps.per.naess";
 
-#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+/// Event reasons, each event is published from a single place
+pub mod reasons {
+    pub const CREATING_DEPLOYMENT: &str = "CreatingDeployment";
+    pub const DELETING_DEPLOYMENT: &str = "DeletingDeployment";
+    pub const DELETE_APPLICATION: &str = "DeleteApplication";
+    pub const INVALID_SPEC: &str = "InvalidSpec";
+    pub const PAUSED: &str = "Paused";
+    pub const PROGRESS_DEADLINE_EXCEEDED: &str = "ProgressDeadlineExceeded";
+    pub const IMAGE_PULL_FAILED: &str = "ImagePullFailed";
+    pub const CRASH_LOOP_BACK_OFF: &str = "CrashLoopBackOff";
+    pub const RUNNING: &str = "Running";
+    pub const STARTING: &str = "Starting";
+    pub const FAILED: &str = "Failed";
+}
+
+/// Annotation freezing an Application, children and status are left alone while it is `"true"`
+pub const PAUSED_ANNOTATION: &str = "customapps.per.naess/paused";
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 enum ApplicationState {
     Running,
     Starting,
     Failed,
 }
+
+impl ApplicationState {
+    /// Reason of the event published when an Application enters this state
+    fn reason(&self) -> &'static str {
+        match self {
+            ApplicationState::Running => reasons::RUNNING,
+            ApplicationState::Starting => reasons::STARTING,
+            ApplicationState::Failed => reasons::FAILED,
+        }
+    }
+}
+
 /// Generate the Kubernetes wrapper struct "Application" from our Spec and Status struct
 ///
 /// This provides a hook for generating the CRD yaml(in crdgen.rs)
 #[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[kube(kind = "Application", group = "per.naess", version = "v1alpha1", namespaced)]
 #[kube(status = "ApplicationStatus", shortname = "app")]
+#[kube(scale = r#"{"specReplicasPath":".spec.replicas", "statusReplicasPath":".status.replicas", "labelSelectorPath":".status.selector"}"#)]
 pub struct ApplicationSpec {
     pub name: String,
-    pub image: String,
+    /// Image of the application container, defaults to the operator's `DEFAULT_IMAGE` when unset
+    pub image: Option<String>,
     pub deploy: bool,
+    /// Name of the application container, defaults to `name` made DNS-1123 compliant
+    pub container_name: Option<String>,
+    /// Number of desired pods, defaults to 1 when unset
+    pub replicas: Option<i32>,
+    /// Port the container listens on
+    pub port: Option<i32>,
+    /// Expose the application through a ClusterIP Service
+    pub expose: Option<ExposeSpec>,
+    /// Route HTTP traffic to the Service through an Ingress, requires `expose`
+    pub ingress: Option<IngressSpec>,
+    /// Environment variables set on the container
+    #[serde(default, deserialize_with = "quantities_from_numbers")]
+    #[schemars(schema_with = "int_or_string_schema::<Option<Vec<EnvVar>>>")]
+    pub env: Option<Vec<EnvVar>>,
+    /// Compute resource requests and limits for the container
+    #[serde(default, deserialize_with = "quantities_from_numbers")]
+    #[schemars(schema_with = "int_or_string_schema::<Option<ResourceRequirements>>")]
+    pub resources: Option<ResourceRequirements>,
+    /// Probe restarting the container when failing
+    #[serde(default)]
+    #[schemars(schema_with = "int_or_string_schema::<Option<Probe>>")]
+    pub liveness_probe: Option<Probe>,
+    /// Probe deciding when the container may receive traffic
+    #[serde(default)]
+    #[schemars(schema_with = "int_or_string_schema::<Option<Probe>>")]
+    pub readiness_probe: Option<Probe>,
+    /// Names of Secrets used to pull the image from a private registry
+    pub image_pull_secrets: Option<Vec<String>>,
+    /// One of `Always`, `IfNotPresent` or `Never`
+    pub image_pull_policy: Option<String>,
+    /// Overrides the image entrypoint
+    pub command: Option<Vec<String>>,
+    /// Overrides the image arguments
+    pub args: Option<Vec<String>>,
+    /// Labels a node must have to run the pods
+    pub node_selector: Option<BTreeMap<String, String>>,
+    /// Taints the pods tolerate
+    pub tolerations: Option<Vec<Toleration>>,
+    /// Pod and node affinity rules
+    pub affinity: Option<Affinity>,
+    /// How the pods are spread across zones, nodes or other topology domains
+    pub topology_spread_constraints: Option<Vec<TopologySpreadConstraint>>,
+    /// Extra labels for managed resources, the operator's own labels win on conflict
+    pub labels: Option<BTreeMap<String, String>>,
+    /// Extra labels only on the Deployment, on top of `labels`
+    pub deployment_labels: Option<BTreeMap<String, String>>,
+    /// Extra labels only on the pods, on top of `labels`
+    pub pod_labels: Option<BTreeMap<String, String>>,
+    /// Extra annotations for managed resources
+    pub annotations: Option<BTreeMap<String, String>>,
+    /// Mount an existing ConfigMap into the container
+    pub config_mount: Option<ConfigMountSpec>,
+    /// Extra pod volumes, e.g. `emptyDir` scratch space or Secrets
+    #[serde(default, deserialize_with = "quantities_from_numbers")]
+    #[schemars(schema_with = "int_or_string_schema::<Option<Vec<Volume>>>")]
+    pub volumes: Option<Vec<Volume>>,
+    /// Mounts of `volumes` into the application container
+    pub volume_mounts: Option<Vec<VolumeMount>>,
+    /// Scale with a HorizontalPodAutoscaler instead of `replicas`
+    pub autoscaling: Option<AutoscalingSpec>,
+    /// Protect the pods with a PodDisruptionBudget
+    #[serde(default)]
+    #[schemars(schema_with = "int_or_string_schema::<Option<PdbSpec>>")]
+    pub pdb: Option<PdbSpec>,
+    /// Extra containers running next to the application container
+    pub sidecars: Option<Vec<ContainerSpec>>,
+    /// Containers run to completion in order before the application container starts
+    pub init_containers: Option<Vec<ContainerSpec>>,
+    /// How pods are replaced on rollout, `RollingUpdate` or `Recreate`
+    #[serde(default)]
+    #[schemars(schema_with = "int_or_string_schema::<Option<DeploymentStrategy>>")]
+    pub strategy: Option<DeploymentStrategy>,
+    /// Old ReplicaSets kept for rollback, the Kubernetes default when unset
+    pub revision_history_limit: Option<i32>,
+    /// Seconds a rollout may stall before it is reported as failed, the Kubernetes default when unset
+    pub progress_deadline_seconds: Option<i32>,
+    /// Seconds pods get to drain after SIGTERM before they are killed, the Kubernetes default when unset
+    pub termination_grace_period_seconds: Option<i64>,
+    /// ServiceAccount the pods run as, the namespace default when unset
+    pub service_account: Option<String>,
+    /// Set to false to not mount the ServiceAccount token into the pods
+    pub automount_service_account_token: Option<bool>,
+    /// Security settings for the pods, e.g. `runAsNonRoot`
+    pub pod_security_context: Option<PodSecurityContext>,
+    /// Security settings for the application container, e.g. `readOnlyRootFilesystem`
+    pub security_context: Option<SecurityContext>,
+    /// Namespace the children are created in, defaults to the Application's namespace
+    ///
+    /// Other namespaces must be listed in the operator's `TARGET_NAMESPACES`. Children there have
+    /// no owner reference and are left behind when this changes
+    pub target_namespace: Option<String>,
+    /// Roll the pods on any spec change, e.g. to pick up a new digest behind a mutable tag
+    #[serde(default)]
+    pub roll_on_config_change: bool,
 }
 
-/// The status object of  `Application`
+impl ApplicationSpec {
+    /// Image of the application container, `image` or the operator wide `default_image`
+    pub fn image(&self, default_image: Option<&str>) -> Option<String> {
+        self.image.clone().or_else(|| default_image.map(String::from))
+    }
+
+    /// Name of the application container, `container_name` or the sanitized `name`
+    pub fn container_name(&self) -> String {
+        self.container_name.clone().unwrap_or_else(|| sanitize_dns1123_label(&self.name))
+    }
+
+    /// Check the parts of the spec the CRD schema does not enforce, `default_image` is used when `image` is unset
+    pub fn validate(&self, default_image: Option<&str>) -> Result<(), String> {
+        let Some(image) = self.image(default_image) else {
+            return Err("No image set and DEFAULT_IMAGE is not configured".into());
+        };
+        if !is_valid_image_ref(&image) {
+            return Err(format!("Invalid image reference `{}`", image));
+        }
+        if let Some(name) = &self.container_name {
+            if !is_dns1123_label(name) {
+                return Err(format!("Invalid container_name `{}`, expected lowercase alphanumerics and `-`", name));
+            }
+        }
+        if self.replicas.is_some_and(|replicas| replicas < 0) {
+            return Err("replicas must not be negative".into());
+        }
+        if self.termination_grace_period_seconds.is_some_and(|seconds| seconds < 0) {
+            return Err("termination_grace_period_seconds must not be negative".into());
+        }
+        let container_name = self.container_name();
+        let mut names = HashSet::from([container_name.as_str()]);
+        let sidecars = self.sidecars.iter().flatten().map(|c| ("sidecar", c));
+        let init_containers = self.init_containers.iter().flatten().map(|c| ("init container", c));
+        for (kind, container) in sidecars.chain(init_containers) {
+            if container.name.is_empty() {
+                return Err(format!("The {} names must not be empty", kind));
+            }
+            if !names.insert(&container.name) {
+                return Err(format!("Duplicate container name `{}`", container.name));
+            }
+            if !is_valid_image_ref(&container.image) {
+                return Err(format!("Invalid image reference `{}` in {} `{}`", container.image, kind, container.name));
+            }
+        }
+        let mut volumes = HashSet::new();
+        if self.config_mount.is_some() {
+            volumes.insert(CONFIG_VOLUME);
+        }
+        for volume in self.volumes.iter().flatten() {
+            if !volumes.insert(&volume.name) {
+                return Err(format!("Duplicate volume name `{}`", volume.name));
+            }
+        }
+        for mount in self.volume_mounts.iter().flatten() {
+            if !volumes.contains(mount.name.as_str()) {
+                return Err(format!("Volume mount `{}` does not reference a volume", mount.name));
+            }
+        }
+        if self.ingress.is_some() && self.expose.is_none() {
+            return Err("ingress requires expose".into());
+        }
+        if let Some(pdb) = &self.pdb {
+            if pdb.min_available.is_some() == pdb.max_unavailable.is_some() {
+                return Err("pdb needs exactly one of min_available or max_unavailable".into());
+            }
+        }
+        if let Some(policy) = &self.image_pull_policy {
+            if !["Always", "IfNotPresent", "Never"].contains(&policy.as_str()) {
+                return Err(format!("Invalid image_pull_policy `{}`, expected Always, IfNotPresent or Never", policy));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An extra container in the pods of an `Application`
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+pub struct ContainerSpec {
+    pub name: String,
+    pub image: String,
+    /// Ports the container listens on
+    pub ports: Option<Vec<i32>>,
+    /// Overrides the image entrypoint
+    pub command: Option<Vec<String>>,
+    /// Overrides the image arguments
+    pub args: Option<Vec<String>>,
+}
+
+/// ConfigMap mounted into an `Application` container, the ConfigMap is not managed by the operator
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+pub struct ConfigMountSpec {
+    /// Name of the ConfigMap
+    pub config_map: String,
+    /// Directory the ConfigMap keys are mounted in
+    pub mount_path: String,
+}
+
+/// HorizontalPodAutoscaler settings for an `Application`
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+pub struct AutoscalingSpec {
+    /// Lower replica bound, defaults to 1
+    pub min_replicas: Option<i32>,
+    /// Upper replica bound
+    pub max_replicas: i32,
+    /// Average CPU utilization in percent to scale towards, defaults to 80
+    pub target_cpu_utilization: Option<i32>,
+}
+
+/// PodDisruptionBudget settings for an `Application`, exactly one field must be set
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+pub struct PdbSpec {
+    /// Number or percentage of pods that must stay available
+    pub min_available: Option<IntOrString>,
+    /// Number or percentage of pods that may be unavailable
+    pub max_unavailable: Option<IntOrString>,
+}
+
+/// Ingress settings for an `Application`
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+pub struct IngressSpec {
+    /// Host matched by the rule, all hosts when unset
+    pub host: Option<String>,
+    /// Path prefix routed to the Service, `/` when unset
+    pub path: Option<String>,
+    /// IngressClass handling the Ingress, the cluster default when unset
+    pub ingress_class_name: Option<String>,
+}
+
+/// Service settings for an `Application`
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+pub struct ExposeSpec {
+    /// Port the Service listens on
+    pub port: i32,
+    /// Container port to forward to, defaults to the application port or `port`
+    pub target_port: Option<i32>,
+    /// Create a headless Service for DNS based pod discovery, toggling it recreates the Service
+    #[serde(default)]
+    pub headless: bool,
+}
+
+/// Whether `name` is a DNS-1123 label, as required for container names
+pub fn is_dns1123_label(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 63
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+/// Closest DNS-1123 label to `name`, e.g. `My_App` becomes `my-app`
+pub fn sanitize_dns1123_label(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .take(63)
+        .collect();
+    let sanitized = sanitized.trim_matches('-');
+    if sanitized.is_empty() { "app".into() } else { sanitized.into() }
+}
+
+/// Whether `image` looks like `[registry[:port]/]path[:tag][@algorithm:digest]`
+pub fn is_valid_image_ref(image: &str) -> bool {
+    let (name, digest) = match image.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (image, None),
+    };
+    if let Some(digest) = digest {
+        let valid_digest = digest.split_once(':').is_some_and(|(algorithm, hex)| {
+            !algorithm.is_empty()
+                && algorithm.chars().all(|c| c.is_ascii_alphanumeric() || "+._-".contains(c))
+                && hex.len() >= 32
+                && hex.chars().all(|c| c.is_ascii_hexdigit())
+        });
+        if !valid_digest {
+            return false;
+        }
+    }
+
+    // A colon after the last slash separates the tag, earlier ones belong to a registry port
+    let (path, tag) = match name.rsplit_once(':') {
+        Some((path, tag)) if !tag.contains('/') => (path, Some(tag)),
+        _ => (name, None),
+    };
+    if let Some(tag) = tag {
+        let valid_tag = tag.len() <= 128
+            && tag.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            && tag.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c));
+        if !valid_tag {
+            return false;
+        }
+    }
+
+    let mut components: Vec<&str> = path.split('/').collect();
+    let first = components[0];
+    let has_registry = components.len() > 1 && (first.contains('.') || first.contains(':') || first == "localhost");
+    if has_registry {
+        let (host, port) = first.split_once(':').unwrap_or((first, "0"));
+        let valid_registry = !host.is_empty()
+            && host.chars().all(|c| c.is_ascii_alphanumeric() || ".-".contains(c))
+            && port.parse::<u16>().is_ok();
+        if !valid_registry {
+            return false;
+        }
+        components.remove(0);
+    }
+
+    components.iter().all(|component| {
+        !component.is_empty()
+            && component.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && component.ends_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+            && component.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c))
+    })
+}
+
+/// Schema for `T` where every k8s-openapi `IntOrString` and `Quantity` accepts both integers and strings
+///
+/// k8s-openapi describes them as `type: string`, which makes the apiserver reject `port: 8080` and `cpu: 1`
+fn int_or_string_schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    fn int_or_string(schema: &mut serde_json::Value) {
+        if let Some(map) = schema.as_object_mut() {
+            map.remove("format");
+            map.remove("type");
+            map.insert("x-kubernetes-int-or-string".into(), true.into());
+        }
+    }
+
+    fn rewrite(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                if map.get("format").and_then(|f| f.as_str()) == Some("int-or-string") {
+                    int_or_string(value);
+                    return;
+                }
+                // Quantity has no format to recognize it by, go by the fields holding one instead
+                if let Some(properties) = map.get_mut("properties").and_then(|p| p.as_object_mut()) {
+                    for (name, property) in properties.iter_mut() {
+                        match name.as_str() {
+                            "limits" | "requests" => property.get_mut("additionalProperties").into_iter().for_each(int_or_string),
+                            "divisor" | "sizeLimit" => int_or_string(property),
+                            _ => (),
+                        }
+                    }
+                }
+                map.values_mut().for_each(rewrite);
+            },
+            serde_json::Value::Array(values) => values.iter_mut().for_each(rewrite),
+            _ => (),
+        }
+    }
+
+    let mut schema = serde_json::to_value(gen.subschema_for::<T>()).expect("schema serializes");
+    rewrite(&mut schema);
+    serde_json::from_value(schema).expect("schema deserializes")
+}
+
+/// Deserialize `T` accepting plain numbers for its `Quantity` fields, the counterpart of [`int_or_string_schema`]
+///
+/// k8s-openapi only parses quantities from strings, but the apiserver keeps `cpu: 1` as an integer.
+/// Quantities are the values of `limits` and `requests`, and the `divisor` and `sizeLimit` fields.
+fn quantities_from_numbers<'de, D: Deserializer<'de>, T: DeserializeOwned>(deserializer: D) -> Result<T, D::Error> {
+    fn stringify(value: &mut serde_json::Value) {
+        if let serde_json::Value::Number(number) = value {
+            *value = number.to_string().into();
+        }
+    }
+
+    fn rewrite(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match key.as_str() {
+                        "limits" | "requests" => value.as_object_mut().into_iter().flat_map(|m| m.values_mut()).for_each(stringify),
+                        "divisor" | "sizeLimit" => stringify(value),
+                        _ => rewrite(value),
+                    }
+                }
+            },
+            serde_json::Value::Array(values) => values.iter_mut().for_each(rewrite),
+            _ => (),
+        }
+    }
+
+    let mut value = serde_json::Value::deserialize(deserializer)?;
+    rewrite(&mut value);
+    serde_json::from_value(value).map_err(serde::de::Error::custom)
+}
+
+/// The status object of  `Application`
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct ApplicationStatus {
     state: ApplicationState,
     deployed: bool,
+    /// The `metadata.generation` this status was computed from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    observed_generation: Option<i64>,
+    /// Ready replicas of the Deployment, read by the scale subresource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replicas: Option<i32>,
+    /// Label selector of the pods, read by the scale subresource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    selector: Option<String>,
+    /// `Available`, `Progressing` and `Degraded` conditions
+    #[serde(default)]
+    conditions: Vec<Condition>,
+    /// Why the Application failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    /// Where the desired replicas come from: `Annotation`, `Spec`, `Default` or `Autoscaling`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replicas_source: Option<String>,
+}
+
+/// Server-side apply body owning only the status of an `Application`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct StatusPatch<'a> {
+    api_version: String,
+    kind: String,
+    status: &'a ApplicationStatus,
+}
+
+impl ApplicationStatus {
+    /// Patch applying this status, apiVersion and kind are taken from `Application`
+    fn apply_patch(&self) -> Patch<StatusPatch<'_>> {
+        Patch::Apply(StatusPatch {
+            api_version: Application::api_version(&()).into_owned(),
+            kind: Application::kind(&()).into_owned(),
+            status: self,
+        })
+    }
 }
 
 impl Application {
@@ -55,20 +511,217 @@ impl Application {
         self.status.as_ref().map(|s| s.deployed).unwrap_or(false)
     }
 
-    async fn reconcile(&self, ctx: Arc<Context>) -> Result<Action, kube::Error> {
+    /// `namespace/name` of an older Application among `apps` creating children with the same name in the same namespace
+    ///
+    /// Children are named after `spec.name`, so only the oldest Application may manage them
+    fn name_owner<I: IntoIterator<Item = Arc<Application>>>(&self, ns: &str, apps: I) -> Option<String> {
+        let key = |app: &Application| (app.creation_timestamp().map(|t| t.0), app.namespace(), app.name_any());
+        let target_ns = self.target_namespace(ns);
+        apps.into_iter()
+            .filter(|other| other.spec.name == self.spec.name && other.uid() != self.uid())
+            .filter(|other| other.namespace().is_some_and(|other_ns| other.target_namespace(&other_ns) == target_ns))
+            .filter(|other| key(other) < key(self))
+            .min_by_key(|other| key(other))
+            .map(|other| format!("{}/{}", other.namespace().unwrap_or_default(), other.name_any()))
+    }
+
+    /// Namespace the children are created in, `target_namespace` or the Application's own `ns`
+    fn target_namespace<'a>(&'a self, ns: &'a str) -> &'a str {
+        self.spec.target_namespace.as_deref().unwrap_or(ns)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.annotations().get(PAUSED_ANNOTATION).is_some_and(|v| v == "true")
+    }
+
+    /// Record a Paused condition once, leaving everything else untouched
+    async fn pause(&self, ctx: &Context, recorder: &Recorder, apps: &Api<Application>) -> Result<Action, kube::Error> {
+        let requeue = Action::requeue(ctx.requeue_after());
+        let previous = self.status.as_ref().map(|s| s.conditions.as_slice()).unwrap_or_default();
+        if previous.iter().any(|c| c.type_ == "Paused" && c.status == "True") {
+            return Ok(requeue);
+        }
+
+        publish(recorder, ctx.dry_run, Event {
+            type_: EventType::Normal,
+            reason: reasons::PAUSED.into(),
+            note: Some(format!("Reconciliation paused by the `{}` annotation", PAUSED_ANNOTATION)),
+            action: "Reconciling".into(),
+            secondary: None,
+        })
+        .await?;
+
+        let mut conditions: Vec<Condition> = previous.iter().filter(|c| c.type_ != "Paused").cloned().collect();
+        conditions.push(condition("Paused", true, "Paused", "Reconciliation is paused", self.metadata.generation, previous));
+        // The schema requires state and deployed, an Application paused before its first reconcile has neither
+        let status = match &self.status {
+            Some(status) => ApplicationStatus { conditions, ..status.clone() },
+            None => ApplicationStatus {
+                state: ApplicationState::Starting,
+                deployed: false,
+                observed_generation: None,
+                replicas: None,
+                selector: None,
+                conditions,
+                message: None,
+                replicas_source: None,
+            },
+        };
+        let new_status = status.apply_patch();
+        if ctx.dry_run {
+            info!("Dry run, not patching status of {}: {:?}", self.name_any(), new_status);
+        } else {
+            let ps = PatchParams::apply(FIELD_MANAGER).force();
+            ctx.patch_status(apps, &self.name_any(), &ps, &new_status).await?;
+        }
+
+        Ok(requeue)
+    }
+
+    /// Record the time from creation until the Deployment was first fully ready
+    fn observe_time_to_ready(&self, ctx: &Context, ns: &str) {
+        let (Some(uid), Some(created)) = (self.uid(), self.creation_timestamp()) else {
+            return;
+        };
+        // Running in the stored status means readiness was observed before a restart
+        let was_running = self.status.as_ref().is_some_and(|s| s.state == ApplicationState::Running);
+        if ctx.became_ready.lock().unwrap().insert(uid) && !was_running {
+            let elapsed = Utc::now().signed_duration_since(created.0);
+            ctx.metrics
+                .time_to_ready
+                .with_label_values(&[ns])
+                .observe(elapsed.num_milliseconds() as f64 / 1000.0);
+        }
+    }
+
+    async fn reconcile(&self, ctx: Arc<Context>, ns: &str) -> Result<Action, kube::Error> {
         let client = ctx.client.clone();
         ctx.diagnostics.write().await.last_event = Utc::now();
         let reporter = ctx.diagnostics.read().await.reporter.clone();
         let recorder = Recorder::new(client.clone(), reporter, self.object_ref(&()));
         let name = self.name_any();
-        let ns = self.namespace().unwrap();
-        let apps: Api<Application> = Api::namespaced(client.clone(), &ns);
+        let apps: Api<Application> = Api::namespaced(client.clone(), ns);
+        let child_api = ctx.child_api();
 
-        let application_state: ApplicationState = ApplicationState::Running;
+        if self.is_paused() {
+            return self.pause(&ctx, &recorder, &apps).await;
+        }
 
-        // Handle deployment
-        let should_deploy = self.spec.deploy;
-        handle_deployment(&self, &ns, client, recorder, &name).await?;
+        // Handle deployment, leaving children untouched while the spec is invalid
+        let target_ns = self.target_namespace(ns);
+        let validation = match self.spec.validate(ctx.default_image.as_deref()).and_then(|()| ctx.limits.check(&self.spec)) {
+            Ok(()) => match self.name_owner(ns, ctx.applications()) {
+                Some(owner) => Err(format!("spec.name `{}` is already used by Application `{}`", self.spec.name, owner)),
+                None => ctx.check_target_namespace(ns, target_ns).await?,
+            },
+            Err(message) => Err(message),
+        };
+        // Never apply over objects the Application does not manage, e.g. a Deployment in kube-system
+        let validation = match validation {
+            Ok(()) if !ctx.observe_only => match foreign_children(self, target_ns, &child_api).await? {
+                Some(message) => Err(message),
+                None => Ok(()),
+            },
+            validation => validation,
+        };
+        let (should_deploy, deployment) = match &validation {
+            // Reflect what exists without touching it
+            Ok(()) if ctx.observe_only => {
+                info!("Observe only, leaving children of {} untouched", name);
+                let deployments: Api<Deployment> = Api::namespaced(client.clone(), target_ns);
+                count_call::<Deployment>(&ctx.metrics.apiserver_calls, "get");
+                let deployment = deployments.get_opt(&self.spec.name).await?;
+                (deployment.is_some(), deployment)
+            },
+            Ok(()) => {
+                let deployment = handle_deployment(self, ctx.default_image.as_deref(), target_ns, &child_api, &recorder).await?;
+                handle_service(self, target_ns, &child_api).await?;
+                handle_ingress(self, target_ns, &child_api).await?;
+                handle_hpa(self, target_ns, &child_api).await?;
+                handle_pdb(self, target_ns, &child_api).await?;
+                (self.spec.deploy, deployment)
+            },
+            Err(message) => {
+                // only send event when the problem changes
+                if self.status.as_ref().and_then(|s| s.message.as_ref()) != Some(message) {
+                    publish(&recorder, ctx.dry_run, Event {
+                        type_: EventType::Warning,
+                        reason: reasons::INVALID_SPEC.into(),
+                        note: Some(message.clone()),
+                        action: "Validating".into(),
+                        secondary: None,
+                    })
+                    .await?;
+                }
+                (self.was_deployed(), None)
+            },
+        };
+
+        let (application_state, ready_replicas, deadline_exceeded) = match (&validation, deployment) {
+            (Err(_), _) => (ApplicationState::Failed, None, false),
+            (Ok(()), Some(d)) => {
+                let status = d.status.unwrap_or_default();
+                let state = deployment_state(
+                    d.spec.and_then(|s| s.replicas).unwrap_or(DEFAULT_REPLICAS),
+                    &status,
+                );
+                (state, Some(status.ready_replicas.unwrap_or(0)), progress_deadline_exceeded(&status))
+            },
+            (Ok(()), None) => (ApplicationState::Running, None, false),
+        };
+        // Pods failing to pull their image or crash looping never become ready, report that instead of Starting forever
+        let pod_failure = match (&application_state, ready_replicas) {
+            (ApplicationState::Starting, Some(_)) => pod_failure(&self.spec, target_ns, &child_api).await?,
+            _ => None,
+        };
+        let application_state = if pod_failure.is_some() { ApplicationState::Failed } else { application_state };
+        if let Some(failure) = &pod_failure {
+            // only send event when the problem changes
+            if self.status.as_ref().and_then(|s| s.message.as_ref()) != Some(&failure.message) {
+                publish(&recorder, ctx.dry_run, Event {
+                    type_: EventType::Warning,
+                    reason: failure.reason.into(),
+                    note: Some(failure.message.clone()),
+                    action: "Reconciling".into(),
+                    secondary: None,
+                })
+                .await?;
+            }
+        }
+        if application_state == ApplicationState::Running && ready_replicas.is_some() {
+            self.observe_time_to_ready(&ctx, ns);
+        }
+        // Record state transitions so `kubectl describe` shows a timeline
+        let previous_state = self.status.as_ref().map(|s| &s.state);
+        if previous_state != Some(&application_state) {
+            let type_ = match application_state {
+                ApplicationState::Failed => EventType::Warning,
+                _ => EventType::Normal,
+            };
+            publish(&recorder, ctx.dry_run, Event {
+                type_,
+                reason: application_state.reason().into(),
+                note: Some(match previous_state {
+                    Some(previous) => format!("State changed from {:?} to {:?}", previous, application_state),
+                    None => format!("State is {:?}", application_state),
+                }),
+                action: "Reconciling".into(),
+                secondary: None,
+            })
+            .await?;
+        }
+        // Counted once per failed rollout
+        if deadline_exceeded && previous_state != Some(&ApplicationState::Failed) {
+            ctx.metrics.rollout_failures.with_label_values(&[ns]).inc();
+            publish(&recorder, ctx.dry_run, Event {
+                type_: EventType::Warning,
+                reason: reasons::PROGRESS_DEADLINE_EXCEEDED.into(),
+                note: Some(format!("Deployment `{}` did not become ready within its progress deadline", self.spec.name)),
+                action: "Reconciling".into(),
+                secondary: None,
+            })
+            .await?;
+        }
 
         // let should_hide = self.spec.hide;
         // if self.was_hidden() && should_hide {
@@ -83,41 +736,84 @@ impl Application {
         //         })
         //         .await?;
         // }
-        // always overwrite status object with what we saw
-        let new_status = Patch::Apply(json!({
-            "apiVersion": "per.naess/v1alpha1",
-            "kind": "Application",
-            "status": ApplicationStatus {
-                state: application_state,
-                deployed: should_deploy
-            }
-        }));
-        let ps = PatchParams::apply("cntrlr").force();
-        let _o = apps.patch_status(&name, &ps, &new_status).await?;
+        // overwrite status object with what we saw
+        let status = self.observed_status(application_state, should_deploy, ready_replicas, validation.err(), pod_failure);
+        // Skip the apiserver round trip when nothing changed
+        if self.status.as_ref() == Some(&status) {
+            return Ok(Action::requeue(ctx.requeue_after()));
+        }
+        let new_status = status.apply_patch();
+        if ctx.dry_run {
+            info!("Dry run, not patching status of {}: {:?}", name, new_status);
+        } else {
+            let ps = PatchParams::apply(FIELD_MANAGER).force();
+            ctx.patch_status(&apps, &name, &ps, &new_status).await?;
+        }
+
+        // If no events were recieved, check back after the configured interval
+        Ok(Action::requeue(ctx.requeue_after()))
+    }
 
-        // If no events were recieved, check back every 5 minutes
-        Ok(Action::requeue(Duration::from_secs(5 * 60)))
+    /// Status recording what a reconcile saw, equal to the current status when nothing changed
+    ///
+    /// Conditions keep their transition time from the current status while unchanged
+    fn observed_status(&self, state: ApplicationState, deployed: bool, ready_replicas: Option<i32>, error: Option<String>, pod_failure: Option<PodFailure>) -> ApplicationStatus {
+        let selector = labels(&self.spec)
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join(",");
+        let previous_conditions = self.status.as_ref().map(|s| s.conditions.as_slice()).unwrap_or_default();
+        let conditions = status_conditions(&state, deployed, error.as_deref(), pod_failure.as_ref(), self.metadata.generation, previous_conditions);
+        let message = error.or(pod_failure.map(|failure| failure.message));
+        ApplicationStatus {
+            state,
+            deployed,
+            observed_generation: self.metadata.generation,
+            replicas: ready_replicas,
+            selector: Some(selector),
+            conditions,
+            message,
+            replicas_source: deployed.then(|| desired_replicas(self).1.to_string()),
+        }
     }
 
     // reconcile with finalize cleanup(object was deleted)
-    async fn cleanup(&self, ctx: Arc<Context>) -> Result<Action, kube::Error> {
+    async fn cleanup(&self, ctx: Arc<Context>, ns: &str) -> Result<Action, kube::Error> {
         let client = ctx.client.clone();
         ctx.diagnostics.write().await.last_event = Utc::now();
         let reporter = ctx.diagnostics.read().await.reporter.clone();
         let recorder = Recorder::new(client.clone(), reporter, self.object_ref(&()));
 
-        let ns = self.namespace().unwrap();
-        cleanup_deployment(&self.spec, &ns, client.clone()).await?;
+        let child_api = ctx.child_api();
 
-        recorder
-            .publish(Event { 
-                type_: EventType::Normal, 
-                reason: "DeleteApplication".into(), 
-                note: Some(format!("Delete `{}`", self.name_any())), 
-                action: "Reconciling".into(), 
-                secondary: None 
-            })
-            .await?;
+        // Children named after spec.name belong to another Application when it is taken
+        let owner = self.name_owner(ns, ctx.applications());
+        let ns = self.target_namespace(ns);
+        if ctx.observe_only {
+            info!("Observe only, leaving children of {} untouched", self.name_any());
+        } else if owner.is_none() {
+            cleanup_ingress(self, ns, &child_api).await?;
+            cleanup_pdb(self, ns, &child_api).await?;
+            cleanup_hpa(self, ns, &child_api).await?;
+            cleanup_service(self, ns, &child_api).await?;
+            // Nothing to delete when the Application never got deployed
+            if self.was_deployed() {
+                cleanup_deployment(self, ns, &child_api, &recorder).await?;
+            }
+        }
+        if let Some(uid) = self.uid() {
+            ctx.became_ready.lock().unwrap().remove(&uid);
+        }
+
+        publish(&recorder, ctx.dry_run, Event {
+            type_: EventType::Normal,
+            reason: reasons::DELETE_APPLICATION.into(),
+            note: Some(format!("Delete `{}`", self.name_any())),
+            action: "Reconciling".into(),
+            secondary: None,
+        })
+        .await?;
 
         Ok(Action::await_change())
     }
@@ -132,108 +828,719 @@ struct Context {
     diagnostics: Arc<RwLock<Diagnostics>>,
     /// Prometheus metrics
     metrics: Metrics,
+    /// Consecutive reconcile failures per object, used for backoff
+    failures: Arc<Mutex<HashMap<String, u32>>>,
+    /// UIDs of Applications whose time to ready has been observed
+    became_ready: Arc<Mutex<HashSet<String>>>,
+    /// Finalizer added to managed Applications
+    finalizer: String,
+    /// Steady state requeue interval
+    requeue_interval: Duration,
+    /// Time after which a reconcile is abandoned and retried
+    reconcile_timeout: Duration,
+    /// Fraction the requeue interval is randomly varied by, spreading out periodic reconciles
+    requeue_jitter: f64,
+    /// Send mutating requests for children as dry run, skipping status patches and events
+    ///
+    /// The finalizer is still added, it is the only write and lets deleted Applications log their dry run cleanup
+    dry_run: bool,
+    /// Set once a status patch found the CRD without status subresource
+    status_subresource_missing: Arc<AtomicBool>,
+    /// Only write status, never create, patch or delete children
+    observe_only: bool,
+    /// Limits reconciles running at once, unlimited when `None`
+    reconcile_permits: Option<Arc<Semaphore>>,
+    /// Limits mutating apiserver calls per second, unlimited when `None`
+    limiter: Option<Arc<ApiRateLimiter>>,
+    /// Size limits protecting the apiserver from huge generated objects
+    limits: SpecLimits,
+    /// Namespaces other than their own that Applications may create children in
+    target_namespaces: HashSet<String>,
+    /// Target namespaces the operator was verified to have RBAC permissions in
+    permitted_namespaces: Arc<Mutex<HashSet<String>>>,
+    /// Applications of every watched scope, kept current by `sync_applications`
+    applications: Vec<Store<Application>>,
+    /// Image used for Applications that do not set `image`
+    default_image: Option<String>,
+}
+
+impl Context {
+    /// Cached Applications of every watched scope
+    fn applications(&self) -> impl Iterator<Item = Arc<Application>> + '_ {
+        self.applications.iter().flat_map(|store| store.state())
+    }
+
+    /// Check the operator may manage children in `target_ns`, passing when it is the Application's `ns`
+    ///
+    /// Successful checks are cached, namespaces not in `TARGET_NAMESPACES` and missing permissions are reported as an invalid spec
+    async fn check_target_namespace(&self, ns: &str, target_ns: &str) -> Result<Result<(), String>, kube::Error> {
+        if let Err(message) = allowed_target_namespace(&self.target_namespaces, ns, target_ns) {
+            return Ok(Err(message));
+        }
+        if ns == target_ns || self.permitted_namespaces.lock().unwrap().contains(target_ns) {
+            return Ok(Ok(()));
+        }
+        match check_child_permissions(self.client.clone(), target_ns).await {
+            Ok(()) => {
+                self.permitted_namespaces.lock().unwrap().insert(target_ns.to_string());
+                Ok(Ok(()))
+            },
+            Err(Error::KubeError(e)) => Err(e),
+            Err(e) => Ok(Err(e.to_string())),
+        }
+    }
+
+    /// Patch the status subresource, or the object itself when the CRD lacks the subresource
+    async fn patch_status<P: Serialize + Debug>(&self, apps: &Api<Application>, name: &str, pp: &PatchParams, patch: &Patch<P>) -> Result<(), kube::Error> {
+        if !self.status_subresource_missing.load(Ordering::Relaxed) {
+            self.metrics.apiserver_calls.with_label_values(&["patch", "applications/status"]).inc();
+            match apps.patch_status(name, pp, patch).await {
+                Ok(_) => return Ok(()),
+                // The object exists, so a 404 means the subresource does not
+                Err(kube::Error::Api(e)) if e.code == 404 && apps.get_opt(name).await?.is_some() => {
+                    warn!("The Application CRD has no status subresource, patching status on the object instead. Reinstall the CRD with `cargo run --bin crdgen | kubectl apply -f -`");
+                    self.status_subresource_missing.store(true, Ordering::Relaxed);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        count_call::<Application>(&self.metrics.apiserver_calls, "patch");
+        apps.patch(name, pp, patch).await?;
+
+        Ok(())
+    }
+
+    /// Client and settings for managing the children of Applications
+    fn child_api(&self) -> ChildApi<'_> {
+        ChildApi {
+            client: self.client.clone(),
+            calls: &self.metrics.apiserver_calls,
+            limiter: self.limiter.as_deref(),
+            dry_run: self.dry_run,
+        }
+    }
+
+    /// Steady state requeue interval varied by up to `requeue_jitter` in both directions
+    fn requeue_after(&self) -> Duration {
+        jittered(self.requeue_interval, self.requeue_jitter)
+    }
+}
+
+/// `interval` scaled by a random factor in `[1 - fraction, 1 + fraction]`
+fn jittered(interval: Duration, fraction: f64) -> Duration {
+    if fraction <= 0.0 {
+        return interval;
+    }
+    interval.mul_f64(1.0 + rand::thread_rng().gen_range(-fraction..=fraction))
+}
+
+/// Upper bounds on list sizes in an `ApplicationSpec`
+#[derive(Clone, Debug)]
+struct SpecLimits {
+    /// Environment variables on the application container, from `SPEC_MAX_ENV`
+    max_env: usize,
+    /// Sidecar containers, from `SPEC_MAX_SIDECARS`
+    max_sidecars: usize,
+    /// Pod volumes, from `SPEC_MAX_VOLUMES`
+    max_volumes: usize,
+}
+
+impl SpecLimits {
+    fn from_env() -> Self {
+        let limit = |var: &str, default: usize| match std::env::var(var) {
+            Ok(limit) => limit.parse().unwrap_or_else(|_| {
+                warn!("Invalid {} `{}`, using {}", var, limit, default);
+                default
+            }),
+            Err(_) => default,
+        };
+        Self {
+            max_env: limit("SPEC_MAX_ENV", 100),
+            max_sidecars: limit("SPEC_MAX_SIDECARS", 10),
+            max_volumes: limit("SPEC_MAX_VOLUMES", 50),
+        }
+    }
+
+    /// Reject specs exceeding any limit
+    fn check(&self, spec: &ApplicationSpec) -> Result<(), String> {
+        let env = spec.env.as_ref().map_or(0, Vec::len);
+        let sidecars = spec.sidecars.as_ref().map_or(0, Vec::len);
+        let volumes = usize::from(spec.config_mount.is_some()) + spec.volumes.as_ref().map_or(0, Vec::len);
+        for (field, count, max) in [("env", env, self.max_env), ("sidecars", sidecars, self.max_sidecars), ("volumes", volumes, self.max_volumes)] {
+            if count > max {
+                return Err(format!("{} has {} entries, at most {} are allowed", field, count, max));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A failed reconcile together with the object it failed for
+#[derive(thiserror::Error, Debug)]
+#[error("reconcile of {namespace}/{name} failed: {source}")]
+struct ReconcileError {
+    namespace: String,
+    name: String,
+    #[source]
+    source: Error,
 }
 
 #[instrument(skip(ctx, app), fields(trace_id))]
-async fn reconcile(app: Arc<Application>, ctx: Arc<Context>) -> Result<Action, Error> {
+async fn reconcile(app: Arc<Application>, ctx: Arc<Context>) -> Result<Action, ReconcileError> {
     let trace_id = telemetry::get_trace_id();
-    Span::current().record("trace_id", &field::display(&trace_id));
+    Span::current().record("trace_id", field::display(&trace_id));
+    // Counts reconciles waiting for a permit or running, kube does not expose its queue
+    ctx.metrics.queue_depth.inc();
+    // Held until the reconcile finishes
+    let _permit = match &ctx.reconcile_permits {
+        Some(permits) => Some(permits.acquire().await.expect("Semaphore is never closed")),
+        None => None,
+    };
     let start = Instant::now();
-    ctx.metrics.reconciliations.inc();
     let client = ctx.client.clone();
     let name = app.name_any();
-    let ns = app.namespace().unwrap();
+    let Some(ns) = app.namespace() else {
+        ctx.metrics.queue_depth.dec();
+        return Err(ReconcileError { namespace: String::new(), name, source: Error::MissingNamespace });
+    };
+    // The finalizer helper adds the finalizer to live objects and removes it after cleanup, also in dry run
+    let had_finalizer = app.finalizers().contains(&ctx.finalizer);
+    ctx.metrics.reconciliations.with_label_values(&[&ns, reconcile_reason(&app, had_finalizer)]).inc();
     let apps: Api<Application> = Api::namespaced(client, &ns);
 
-    let action = finalizer(&apps, CUSTOM_APP_FINALIZER, app, |event| async {
+    let deleting = app.meta().deletion_timestamp.is_some();
+    let finalized = finalizer(&apps, &ctx.finalizer, app, |event| async {
         match event {
-           finalizer::Event::Apply(app) =>  app.reconcile(ctx.clone()).await,
-           finalizer::Event::Cleanup(app) => app.cleanup(ctx.clone()).await,
+           finalizer::Event::Apply(app) =>  app.reconcile(ctx.clone(), &ns).await,
+           finalizer::Event::Cleanup(app) => app.cleanup(ctx.clone(), &ns).await,
         }
-    })
-    .await
-    .map_err(Error::FinalizerError);
+    });
+    // A hung apiserver call fails the reconcile instead of holding it forever
+    let action = with_timeout(ctx.reconcile_timeout, async { finalized.await.map_err(Error::FinalizerError) }).await;
 
+    ctx.metrics.queue_depth.dec();
+    if action.is_ok() {
+        match (had_finalizer, deleting) {
+            (false, false) => ctx.metrics.finalizer_added.with_label_values(&[&ns]).inc(),
+            (true, true) => ctx.metrics.finalizer_removed.with_label_values(&[&ns]).inc(),
+            _ => (),
+        }
+    }
     let duration = start.elapsed().as_millis() as f64 / 1000.0;
+    let result = if action.is_ok() { "success" } else { "error" };
     ctx.metrics
         .reconcile_duration
-        .with_label_values(&[])
+        .with_label_values(&[&ns, result])
         .observe(duration);
 
     info!("Reconciled Application \"{}\" in {}", name, ns);
-    action
-}
-
-async fn handle_deployment(app: &Application, ns: &str, client: Client, recorder: Recorder, name: &str) -> Result<(), kube::Error> {
-    let should_deploy = app.spec.deploy;
-    if app.was_deployed() && should_deploy {
-        create_deployment(&app.spec, ns, client).await?;
-        recorder.publish(Event { 
-            type_: EventType::Normal, 
-            reason: "CreatingDeployment".into(), 
-            note: Some(format!("Creating deployment `{}`", name)), 
-            action: "Reconciling".into(), 
-            secondary: None, 
-        })
-        .await?;
-    } else if app.was_deployed() && !should_deploy {
-        cleanup_deployment(&app.spec, &ns, client).await?;
-        recorder.publish(Event { 
-            type_: EventType::Normal, 
-            reason: "DeletingDeployment".into(), 
-            note: Some(format!("Deleting deployment `{}`", name)), 
-            action: "Reconciling".into(), 
-            secondary: None, 
-        })
-        .await?;
+    {
+        let mut diagnostics = ctx.diagnostics.write().await;
+        diagnostics.reconcile_count += 1;
+        diagnostics.last_reconciled_object = Some(format!("{}/{}", ns, name));
+        if let Err(e) = &action {
+            diagnostics.last_error = Some(e.to_string());
+        }
+    }
+    match action {
+        Ok(action) => {
+            ctx.failures.lock().unwrap().remove(&format!("{}/{}", ns, name));
+            Ok(action)
+        },
+        Err(source) => Err(ReconcileError { namespace: ns, name, source }),
+    }
+}
+
+/// Run `reconcile`, failing with `Error::Timeout` when it takes longer than `timeout`
+async fn with_timeout<F: Future<Output = Result<Action, Error>>>(timeout: Duration, reconcile: F) -> Result<Action, Error> {
+    tokio::time::timeout(timeout, reconcile).await.unwrap_or(Err(Error::Timeout(timeout)))
+}
+
+/// What the object says about why it is reconciled
+///
+/// Only the first reconcile finds no finalizer, so it alone counts as `created`, also in dry run where no status is written.
+/// The controller does not expose its triggers, so owned Deployment changes and
+/// periodic requeues both count as `unknown`
+fn reconcile_reason(app: &Application, had_finalizer: bool) -> &'static str {
+    if app.meta().deletion_timestamp.is_some() {
+        "deleted"
+    } else if !had_finalizer {
+        "created"
+    } else if app.status.as_ref().is_some_and(|s| s.observed_generation != app.metadata.generation) {
+        "spec_changed"
+    } else {
+        "unknown"
+    }
+}
+
+/// Create, update or remove the Deployment of an Application, returning it when it should exist
+async fn handle_deployment(app: &Application, default_image: Option<&str>, ns: &str, child_api: &ChildApi<'_>, recorder: &Recorder) -> Result<Option<Deployment>, kube::Error> {
+    // The deployment helpers publish their own events
+    let deployment = match (app.was_deployed(), app.spec.deploy) {
+        // First time deploying
+        (false, true) => Some(create_deployment(app, default_image, ns, child_api, recorder).await?),
+        // Already deployed, make sure the deployment still exists and follows the spec
+        (true, true) => Some(create_deployment(app, default_image, ns, child_api, recorder).await?),
+        // Deploy was switched off
+        (true, false) => {
+            cleanup_deployment(app, ns, child_api, recorder).await?;
+            None
+        },
+        // Nothing to do
+        (false, false) => None,
+    };
+
+    Ok(deployment)
+}
+
+/// Whether the rollout of a Deployment stalled past its `progressDeadlineSeconds`
+fn progress_deadline_exceeded(status: &DeploymentStatus) -> bool {
+    status.conditions.iter().flatten().any(|c| {
+        c.type_ == "Progressing" && c.status == "False" && c.reason.as_deref() == Some("ProgressDeadlineExceeded")
+    })
+}
+
+/// Map the rollout status of a Deployment with `desired` replicas to an `ApplicationState`
+fn deployment_state(desired: i32, status: &DeploymentStatus) -> ApplicationState {
+    let conditions = status.conditions.as_deref().unwrap_or_default();
+    let failed = conditions.iter().any(|c| {
+        (c.type_ == "Progressing" && c.status == "False")
+            || (c.type_ == "ReplicaFailure" && c.status == "True")
+    });
+    let ready = status.ready_replicas.unwrap_or(0);
+    let updated = status.updated_replicas.unwrap_or(0);
+
+    if failed {
+        ApplicationState::Failed
+    } else if ready >= desired && updated >= desired {
+        ApplicationState::Running
+    } else {
+        ApplicationState::Starting
+    }
+}
+
+async fn handle_hpa(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    match (&app.spec.autoscaling, app.spec.deploy) {
+        (Some(autoscaling), true) => {
+            create_hpa(app, autoscaling, ns, child_api).await?;
+        },
+        _ => cleanup_hpa(app, ns, child_api).await?,
     }
 
     Ok(())
 }
 
+async fn handle_pdb(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    match (&app.spec.pdb, app.spec.deploy) {
+        (Some(pdb), true) => {
+            create_pdb(app, pdb, ns, child_api).await?;
+        },
+        _ => cleanup_pdb(app, ns, child_api).await?,
+    }
+
+    Ok(())
+}
+
+async fn handle_ingress(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    match (&app.spec.ingress, &app.spec.expose, app.spec.deploy) {
+        (Some(ingress), Some(expose), true) => {
+            create_ingress(app, ingress, expose, ns, child_api).await?;
+        },
+        _ => cleanup_ingress(app, ns, child_api).await?,
+    }
+
+    Ok(())
+}
+
+async fn handle_service(app: &Application, ns: &str, child_api: &ChildApi<'_>) -> Result<(), kube::Error> {
+    match (&app.spec.expose, app.spec.deploy) {
+        (Some(expose), true) => {
+            create_service(app, expose, ns, child_api).await?;
+        },
+        _ => cleanup_service(app, ns, child_api).await?,
+    }
+
+    Ok(())
+}
+
+/// Build a condition, keeping the previous transition time when its status did not change
+fn condition(type_: &str, status: bool, reason: &str, message: &str, generation: Option<i64>, previous: &[Condition]) -> Condition {
+    let status = if status { "True" } else { "False" };
+    let last_transition_time = previous
+        .iter()
+        .find(|c| c.type_ == type_ && c.status == status)
+        .map(|c| c.last_transition_time.clone())
+        .unwrap_or_else(|| Time(Utc::now()));
+
+    Condition {
+        type_: type_.into(),
+        status: status.into(),
+        reason: reason.into(),
+        message: message.into(),
+        last_transition_time,
+        observed_generation: generation,
+    }
+}
+
+/// Kubernetes style conditions describing an `ApplicationState`
+fn status_conditions(state: &ApplicationState, deployed: bool, error: Option<&str>, pod_failure: Option<&PodFailure>, generation: Option<i64>, previous: &[Condition]) -> Vec<Condition> {
+    let (failed_reason, failed_message) = match pod_failure {
+        Some(failure) => (failure.reason, failure.message.as_str()),
+        None => ("Failed", "Deployment failed to roll out"),
+    };
+    let (available, progressing, degraded, reason, message) = match (error, deployed, state) {
+        (Some(error), _, _) => (false, false, true, "InvalidSpec", error),
+        (None, false, _) => (false, false, false, "NotDeployed", "Application is not deployed"),
+        (None, true, ApplicationState::Running) => (true, false, false, "Running", "All replicas are ready"),
+        (None, true, ApplicationState::Starting) => (false, true, false, "Starting", "Waiting for replicas to become ready"),
+        (None, true, ApplicationState::Failed) => (false, false, true, failed_reason, failed_message),
+    };
+
+    vec![
+        condition("Available", available, reason, message, generation, previous),
+        condition("Progressing", progressing, reason, message, generation, previous),
+        condition("Degraded", degraded, reason, message, generation, previous),
+    ]
+}
+
+/// Crate version of the operator
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Commit the operator was built from, `unknown` outside a git checkout
+pub const GIT_SHA: &str = env!("GIT_SHA");
+
 // Prometheus metrics exposed on /metrics
 #[derive(Clone)]
 pub struct Metrics {
-    pub reconciliations: IntCounter,
-    pub failures: IntCounter,
+    pub reconciliations: IntCounterVec,
+    pub failures: IntCounterVec,
     pub reconcile_duration: HistogramVec,
+    pub time_to_ready: HistogramVec,
+    pub build_info: IntGaugeVec,
+    pub queue_depth: IntGauge,
+    pub rollout_failures: IntCounterVec,
+    pub finalizer_added: IntCounterVec,
+    pub finalizer_removed: IntCounterVec,
+    pub seconds_since_last_reconcile: Gauge,
+    pub apiserver_calls: IntCounterVec,
+    /// Registry holding the metrics above, separate from the global default registry
+    pub registry: Registry,
 }
 
 impl Metrics {
     fn new() -> Self {
-        let reconcile_histogram = register_histogram_vec!(
+        let registry = Registry::new();
+        let reconcile_histogram = register_histogram_vec_with_registry!(
             "app_controller_reconcile_duration_seconds",
             "The duration of reconcile to complete in seconds",
-            &[],
-            vec![0.01, 0.1, 0.25, 0.5, 1., 5., 15., 60.]
+            &["namespace", "result"],
+            vec![0.01, 0.1, 0.25, 0.5, 1., 5., 15., 60.],
+            registry
+        )
+        .unwrap();
+
+        let build_info = register_int_gauge_vec_with_registry!(
+            "app_controller_build_info",
+            "Build information of the running operator, always 1",
+            &["version", "git_sha"],
+            registry
         )
         .unwrap();
+        build_info.with_label_values(&[VERSION, GIT_SHA]).set(1);
 
         Metrics { 
-            reconciliations: register_int_counter!("app_controller_reconciliations_total", "reconciliations").unwrap(), 
-            failures: register_int_counter!(
+            reconciliations: register_int_counter_vec_with_registry!(
+                "app_controller_reconciliations_total",
+                "reconciliations",
+                &["namespace", "reason"],
+                registry
+            ).unwrap(), 
+            failures: register_int_counter_vec_with_registry!(
                 "app_controller_reconciliation_errors_total",
-                "reconciliation errors"
+                "reconciliation errors",
+                &["namespace", "error"],
+                registry
             ).unwrap(), 
-            reconcile_duration: reconcile_histogram 
+            reconcile_duration: reconcile_histogram,
+            time_to_ready: register_histogram_vec_with_registry!(
+                "app_controller_time_to_ready_seconds",
+                "Time from Application creation until all replicas were first ready",
+                &["namespace"],
+                vec![5., 15., 30., 60., 120., 300., 600., 1800.],
+                registry
+            ).unwrap(),
+            build_info,
+            queue_depth: register_int_gauge_with_registry!(
+                "app_controller_reconcile_queue_depth",
+                "Reconciles waiting to run or running",
+                registry
+            ).unwrap(),
+            rollout_failures: register_int_counter_vec_with_registry!(
+                "app_controller_rollout_failures_total",
+                "Deployments that exceeded their progress deadline",
+                &["namespace"],
+                registry
+            ).unwrap(),
+            finalizer_added: register_int_counter_vec_with_registry!(
+                "app_controller_finalizer_added_total",
+                "Finalizers added to Applications",
+                &["namespace"],
+                registry
+            ).unwrap(),
+            finalizer_removed: register_int_counter_vec_with_registry!(
+                "app_controller_finalizer_removed_total",
+                "Finalizers removed from deleted Applications",
+                &["namespace"],
+                registry
+            ).unwrap(),
+            seconds_since_last_reconcile: register_gauge_with_registry!(
+                "app_controller_seconds_since_last_reconcile",
+                "Seconds since the most recent reconcile of any Application",
+                registry
+            ).unwrap(),
+            apiserver_calls: register_int_counter_vec_with_registry!(
+                "app_controller_apiserver_calls_total",
+                "Apiserver calls made while reconciling Applications",
+                &["verb", "resource"],
+                registry
+            ).unwrap(),
+            registry,
         }
     }
 }
 
+/// Name of this operator replica from `POD_NAME`, falling back to `HOSTNAME`
+fn instance_identity() -> Option<String> {
+    std::env::var("POD_NAME").or_else(|_| std::env::var("HOSTNAME")).ok()
+}
+
 // Diagnostics to be exposed on webserver
 #[derive(Clone, Serialize)]
 pub struct Diagnostics {
-    #[serde(deserialize_with = "from_ts")]
-    pub last_event: DateTime<Utc> ,
+    /// Time of the last reconcile, serialized as RFC3339
+    pub last_event: DateTime<Utc>,
     #[serde(skip)]
     pub reporter: Reporter,
+    /// Namespaces watched for Applications, empty when watching all namespaces
+    pub watch_namespaces: Vec<String>,
+    /// Whether this replica runs the controller, always true without leader election
+    pub leader: bool,
+    /// `namespace/name` of the most recently reconciled Application
+    pub last_reconciled_object: Option<String>,
+    /// Most recent reconcile error
+    pub last_error: Option<String>,
+    /// Number of reconciles since startup
+    pub reconcile_count: u64,
+    /// Crate version of the running operator
+    pub version: &'static str,
+    /// Commit the operator was built from
+    pub git_sha: &'static str,
+    /// Time the operator started, serialized as RFC3339
+    pub started_at: DateTime<Utc>,
 }
 
 impl Diagnostics {
-    fn new() -> Self {
+    fn new(watch_namespaces: Vec<String>, leader: bool) -> Self {
         Self {
             last_event: Utc::now(),
-            reporter: "app-reporter".into()
+            reporter: Reporter {
+                controller: "app-reporter".into(),
+                instance: instance_identity(),
+            },
+            watch_namespaces,
+            leader,
+            last_reconciled_object: None,
+            last_error: None,
+            reconcile_count: 0,
+            version: VERSION,
+            git_sha: GIT_SHA,
+            started_at: Utc::now(),
+        }
+    }
+}
+
+/// Reconcile timeout from `RECONCILE_TIMEOUT_SECS`, 60 seconds by default
+///
+/// Zero is rejected as it would time out every reconcile before its first apiserver call
+fn reconcile_timeout() -> Duration {
+    let default = Duration::from_secs(60);
+    match std::env::var("RECONCILE_TIMEOUT_SECS") {
+        Ok(secs) => match secs.parse::<u64>() {
+            Ok(parsed) if parsed > 0 => Duration::from_secs(parsed),
+            _ => {
+                warn!("Invalid RECONCILE_TIMEOUT_SECS `{}`, using {:?}", secs, default);
+                default
+            },
+        },
+        Err(_) => default,
+    }
+}
+
+/// Requeue jitter from `REQUEUE_JITTER` between 0 and 1, 0.1 by default
+fn requeue_jitter() -> f64 {
+    let default = 0.1;
+    match std::env::var("REQUEUE_JITTER") {
+        Ok(jitter) => match jitter.parse::<f64>() {
+            Ok(jitter) if (0.0..1.0).contains(&jitter) => jitter,
+            _ => {
+                warn!("Invalid REQUEUE_JITTER `{}`, using {}", jitter, default);
+                default
+            },
+        },
+        Err(_) => default,
+    }
+}
+
+/// Limit from `MAX_CONCURRENT_RECONCILES`, unlimited when unset or 0
+fn max_concurrent_reconciles() -> Option<usize> {
+    let limit = std::env::var("MAX_CONCURRENT_RECONCILES").ok()?;
+    match limit.parse() {
+        Ok(0) => None,
+        Ok(limit) => Some(limit),
+        Err(_) => {
+            warn!("Invalid MAX_CONCURRENT_RECONCILES `{}`, not limiting reconciles", limit);
+            None
+        },
+    }
+}
+
+/// Namespaces Applications may target besides their own from `TARGET_NAMESPACES`, none when unset
+fn target_namespaces() -> HashSet<String> {
+    let namespaces: HashSet<String> = std::env::var("TARGET_NAMESPACES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|ns| ns.trim())
+        .filter(|ns| !ns.is_empty())
+        .map(String::from)
+        .collect();
+    if !namespaces.is_empty() {
+        info!("Applications may target namespaces {:?}", namespaces);
+    }
+    namespaces
+}
+
+/// Whether an Application in `ns` may create children in `target_ns`, it always may in its own namespace
+fn allowed_target_namespace(allowed: &HashSet<String>, ns: &str, target_ns: &str) -> Result<(), String> {
+    if ns == target_ns || allowed.contains(target_ns) {
+        Ok(())
+    } else {
+        Err(format!("target_namespace `{}` is not in the operator's TARGET_NAMESPACES", target_ns))
+    }
+}
+
+/// Drive a controller forever, restarting its watches with backoff when they fail
+async fn run_controller(apps: Api<Application>, deployments: Api<Deployment>, watched: ListParams, owned: ListParams, context: Arc<Context>) {
+    let mut restarts = 0;
+    loop {
+        let mut stream = Controller::new(apps.clone(), watched.clone())
+            .owns(deployments.clone(), owned.clone())
+            .run(reconcile, error_policy, context.clone())
+            .boxed();
+        while let Some(result) = stream.next().await {
+            match result {
+                Ok(_) => restarts = 0,
+                // Already logged by error_policy
+                Err(controller::Error::ReconcilerFailed(..)) => (),
+                Err(controller::Error::ObjectNotFound(obj)) => warn!("Object {} vanished before it was reconciled", obj),
+                // The watcher backs off and resumes by itself, restarting would drop the queue and relist everything
+                Err(controller::Error::QueueError(e)) => warn!(error = watch_error_kind(&e), "Watch failed: {}", e),
+            }
+        }
+
+        restarts += 1;
+        let delay = backoff(restarts);
+        warn!("Controller stopped, restarting in {:?}", delay);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Mirror the Applications of a scope into `writer`, counting the scope as synced once the initial list completed
+///
+/// `ready` is set when the last scope syncs, even when there are no Applications to reconcile
+///
+/// This is a second watch next to the Controller's own, `Controller::store()` cannot replace it:
+/// the Controller only runs while holding the lease and is rebuilt with an empty store on every restart,
+/// while standbys need a synced store to report ready and reconciles need it filled before they start.
+/// kube 0.74 has no way to hand the Controller an existing reflector
+async fn sync_applications(apps: Api<Application>, watched: ListParams, writer: reflector::store::Writer<Application>, unsynced: Arc<AtomicUsize>, ready: Arc<watch::Sender<bool>>) {
+    let mut synced = false;
+    let mut events = reflector(writer, watcher(apps, watched)).backoff(watcher::default_backoff()).boxed();
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(watcher::Event::Restarted(_)) if !synced => {
+                synced = true;
+                if unsynced.fetch_sub(1, Ordering::Relaxed) == 1 {
+                    info!("Initial sync of Applications complete");
+                    ready.send_replace(true);
+                }
+            },
+            Ok(_) => (),
+            Err(e) => warn!(error = watch_error_kind(&e), "Application sync failed: {}", e),
+        }
+    }
+}
+
+/// Short label for a watch error, used in logs
+fn watch_error_kind(error: &watcher::Error) -> &'static str {
+    match error {
+        watcher::Error::InitialListFailed(_) => "initial_list",
+        watcher::Error::WatchStartFailed(_) => "watch_start",
+        watcher::Error::WatchError(e) if e.code == 410 => "watch_expired",
+        watcher::Error::WatchError(_) => "watch_error",
+        watcher::Error::WatchFailed(_) => "watch_failed",
+        watcher::Error::TooManyObjects => "too_many_objects",
+    }
+}
+
+/// Mutating apiserver calls per second from `API_RATE_LIMIT`, unlimited when unset or 0
+fn api_rate_limit() -> Option<NonZeroU32> {
+    let limit = std::env::var("API_RATE_LIMIT").ok()?;
+    match limit.parse::<u32>() {
+        Ok(limit) => {
+            let limit = NonZeroU32::new(limit);
+            if let Some(limit) = limit {
+                info!("Limiting mutating apiserver calls to {} per second", limit);
+            }
+            limit
+        },
+        Err(_) => {
+            warn!("Invalid API_RATE_LIMIT `{}`, not limiting apiserver calls", limit);
+            None
+        },
+    }
+}
+
+/// How long to wait for the CRD from `CRD_WAIT_TIMEOUT_SECS`, 60 seconds by default
+fn crd_wait_timeout() -> Duration {
+    let default = Duration::from_secs(60);
+    match std::env::var("CRD_WAIT_TIMEOUT_SECS") {
+        Ok(secs) => secs.parse().map(Duration::from_secs).unwrap_or_else(|_| {
+            warn!("Invalid CRD_WAIT_TIMEOUT_SECS `{}`, using {:?}", secs, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// List Applications until the CRD is served, retrying with backoff for up to `timeout`
+///
+/// The CRD and operator are often applied together, so a missing CRD may just not have landed yet
+async fn wait_for_crd(apps: &Api<Application>, lp: &ListParams, timeout: Duration) -> Result<(), Error> {
+    let deadline = Instant::now() + timeout;
+    let mut attempts = 0;
+    loop {
+        match apps.list(&lp.clone().limit(1)).await {
+            Ok(_) => return Ok(()),
+            Err(kube::Error::Api(e)) if e.code == 404 => {
+                attempts += 1;
+                let delay = backoff(attempts);
+                if Instant::now() + delay > deadline {
+                    return Err(Error::CrdNotInstalled(kube::Error::Api(e)));
+                }
+                warn!("Application CRD not found, retrying in {:?}", delay);
+                tokio::time::sleep(delay).await;
+            },
+            Err(e) => return Err(Error::KubeError(e)),
         }
     }
 }
@@ -243,12 +1550,29 @@ impl Diagnostics {
 pub struct Operator {
     /// Diagnostics populated by the reconciler
     diagnostics: Arc<RwLock<Diagnostics>>,
+    /// Set once the Applications in every watched scope have been listed
+    ready: watch::Receiver<bool>,
+    /// Prometheus metrics shared with the reconciler
+    metrics: Metrics,
+}
+
+/// Exponential backoff after consecutive failures: 1s, 2s, 4s... capped at 5 minutes
+fn backoff(failures: u32) -> Duration {
+    let max = Duration::from_secs(5 * 60);
+    2u32.checked_pow(failures.saturating_sub(1))
+        .map(|factor| Duration::from_secs(factor.into()))
+        .map_or(max, |delay| delay.min(max))
 }
 
-fn error_policy(error: &Error, ctx: Arc<Context>) -> Action {
+fn error_policy(error: &ReconcileError, ctx: Arc<Context>) -> Action {
     warn!("reconcile failed: {:?}", error);
-    ctx.metrics.failures.inc();
-    Action::requeue(Duration::from_secs(5 * 60))
+    ctx.metrics.failures
+        .with_label_values(&[&error.namespace, error_kind(&error.source)])
+        .inc();
+    let mut failures = ctx.failures.lock().unwrap();
+    let count = failures.entry(format!("{}/{}", error.namespace, error.name)).or_default();
+    *count = count.saturating_add(1);
+    Action::requeue(backoff(*count))
 }
 
 /// Operator that owns a Controller for Application
@@ -257,37 +1581,157 @@ impl Operator {
     ///
     /// This returns a `Operator` that drives a `Controller` + a future to be awaited
     /// It is up to `main` to wait for the controller stream
-    pub async fn new() -> (Self, BoxFuture<'static, ()>) {
-        let client = Client::try_default().await.expect("Create Client");
+    ///
+    /// Watches `namespaces`, or all namespaces when empty, and requeues Applications every `requeue_interval`.
+    /// Applications without `image` run `default_image`
+    pub async fn new(namespaces: Vec<String>, requeue_interval: Duration, default_image: Option<String>) -> Result<(Self, BoxFuture<'static, ()>), Error> {
+        let client = Client::try_default().await.map_err(Error::KubeError)?;
         let metrics = Metrics::new();
-        let diagnostics = Arc::new(RwLock::new(Diagnostics::new()));
+        let namespaces: Vec<String> = namespaces
+            .iter()
+            .map(|ns| ns.trim())
+            .filter(|ns| !ns.is_empty())
+            .map(String::from)
+            .collect();
+        let leader_election = std::env::var("LEADER_ELECTION").is_ok_and(|v| v == "true");
+        let diagnostics = Arc::new(RwLock::new(Diagnostics::new(namespaces.clone(), !leader_election)));
+        let (ready_tx, ready) = watch::channel(false);
+        let ready_tx = Arc::new(ready_tx);
+        // One store of Applications per watched namespace, or a single cluster wide store
+        let (stores, writers): (Vec<_>, Vec<_>) = (0..namespaces.len().max(1))
+            .map(|_| {
+                let writer = reflector::store::Writer::<Application>::default();
+                (writer.as_reader(), writer)
+            })
+            .unzip();
+        let max_concurrent = max_concurrent_reconciles();
+        match max_concurrent {
+            Some(limit) => info!("Running at most {} reconciles at once", limit),
+            None => info!("Not limiting concurrent reconciles"),
+        }
         let context = Arc::new(Context {
             client: client.clone(),
             metrics: metrics.clone(),
             diagnostics: diagnostics.clone(),
+            failures: Arc::default(),
+            became_ready: Arc::default(),
+            finalizer: std::env::var("FINALIZER_NAME").unwrap_or_else(|_| CUSTOM_APP_FINALIZER.into()),
+            requeue_interval,
+            requeue_jitter: requeue_jitter(),
+            reconcile_timeout: reconcile_timeout(),
+            dry_run: std::env::var("DRY_RUN").is_ok_and(|v| v == "true"),
+            status_subresource_missing: Arc::default(),
+            observe_only: std::env::var("OBSERVE_ONLY").is_ok_and(|v| v == "true"),
+            reconcile_permits: max_concurrent.map(|limit| Arc::new(Semaphore::new(limit))),
+            target_namespaces: target_namespaces(),
+            permitted_namespaces: Arc::default(),
+            limits: SpecLimits::from_env(),
+            limiter: api_rate_limit().map(|limit| Arc::new(RateLimiter::direct(Quota::per_second(limit)))),
+            applications: stores,
+            default_image,
         });
 
-        let apps = Api::<Application>::all(client);
-        //Ensure CRD is installed before loop-watching
-        let _r = apps
-            .list(&ListParams::default().limit(1))
-            .await
-            .expect("Is the crd installed? please run: cargo run --bin crdgen | kubectl apply -f -");
-
-        // All good. Start controller and return its future.
-        let controller = Controller::new(apps, ListParams::default())
-            .run(reconcile, error_policy, context)
-            .filter_map(|x| async move { std::result::Result::ok(x) })
-            .for_each(|_| futures::future::ready(()))
-            .boxed();
+        // One pair of Apis per watched namespace, or a single cluster wide pair
+        let apis: Vec<(Api<Application>, Api<Deployment>)> = if namespaces.is_empty() {
+            info!("Watching Applications in all namespaces");
+            vec![(Api::all(client.clone()), Api::all(client.clone()))]
+        } else {
+            info!("Watching Applications in namespaces {:?}", namespaces);
+            namespaces
+                .iter()
+                .map(|ns| (Api::namespaced(client.clone(), ns), Api::namespaced(client.clone(), ns)))
+                .collect()
+        };
+        // Changes to managed Deployments trigger a reconcile of their owning Application
+        let owned = ListParams::default().labels(&format!("app.kubernetes.io/managed-by={}", MANAGED_BY));
+
+        // Applications not matching WATCH_SELECTOR are ignored, allowing to shard between operators
+        let watched = match std::env::var("WATCH_SELECTOR") {
+            Ok(selector) if !selector.is_empty() => {
+                info!("Only managing Applications matching `{}`", selector);
+                ListParams::default().labels(&selector)
+            },
+            _ => ListParams::default(),
+        };
+
+        // Fail fast on missing RBAC rules rather than with 403s while reconciling
+        let scopes: Vec<Option<&str>> = if namespaces.is_empty() {
+            vec![None]
+        } else {
+            namespaces.iter().map(|ns| Some(ns.as_str())).collect()
+        };
+        for scope in scopes {
+            check_permissions(client.clone(), scope).await?;
+        }
+        let elector = if leader_election {
+            let identity = instance_identity().unwrap_or_else(|| "customapps-operator".into());
+            let elector = LeaderElector::new(client.clone(), identity, Duration::from_secs(15));
+            check_lease_permissions(client.clone(), elector.namespace()).await?;
+            Some(elector)
+        } else {
+            None
+        };
+        let crd_wait = crd_wait_timeout();
 
-        
-        (Self { diagnostics }, controller)
+        let mut controllers = Vec::with_capacity(apis.len());
+        let mut syncs = Vec::with_capacity(apis.len());
+        let unsynced = Arc::new(AtomicUsize::new(apis.len()));
+        for ((apps, deployments), writer) in apis.into_iter().zip(writers) {
+            //Ensure CRD is installed before loop-watching
+            wait_for_crd(&apps, &watched, crd_wait).await?;
+
+            syncs.push(sync_applications(apps.clone(), watched.clone(), writer, unsynced.clone(), ready_tx.clone()));
+            controllers.push(run_controller(apps, deployments, watched.clone(), owned.clone(), context.clone()));
+        }
+
+        // All good. Start controllers and return their future.
+        let syncs = futures::future::join_all(syncs);
+        let mut synced = ready.clone();
+        let controller = async move {
+            // Reconciles look up conflicting Applications in the stores, wait until they are filled
+            while !*synced.borrow() {
+                if synced.changed().await.is_err() {
+                    return;
+                }
+            }
+            futures::future::join_all(controllers).await;
+        }
+        .boxed();
+        let controller = if let Some(elector) = elector {
+            let diagnostics = diagnostics.clone();
+            async move {
+                // Only run the controllers while holding the lease
+                elector.acquire().await;
+                diagnostics.write().await.leader = true;
+                tokio::select! {
+                    _ = controller => (),
+                    _ = elector.hold() => warn!("Lost leadership"),
+                }
+                diagnostics.write().await.leader = false;
+            }
+            .boxed()
+        } else {
+            controller
+        };
+        // Syncing runs regardless of leadership, it only reads
+        let controller = futures::future::join(syncs, controller).map(|_| ()).boxed();
+
+        Ok((Self { diagnostics, ready, metrics }, controller))
     }
 
-    /// Metrics
-    pub fn metrics(&self) -> Vec<MetricFamily> {
-        default_registry().gather()
+    /// Metrics, refreshing the gauges computed on scrape
+    pub async fn metrics(&self) -> Vec<MetricFamily> {
+        let last_event = self.diagnostics.read().await.last_event;
+        let since = Utc::now().signed_duration_since(last_event);
+        self.metrics.seconds_since_last_reconcile.set(since.num_milliseconds() as f64 / 1000.0);
+        self.metrics.registry.gather()
+    }
+
+    /// Whether the initial list of Applications completed in every watched scope
+    ///
+    /// Replicas waiting for the leader lease sync too, so a standby is ready to take over
+    pub fn ready(&self) -> bool {
+        *self.ready.borrow()
     }
 
     /// State getter
@@ -295,3 +1739,718 @@ impl Operator {
         self.diagnostics.read().await.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+    use crate::deployment::OWNER_ANNOTATION;
+    use serde_json::json;
+
+    /// `spec` merged over a minimal valid spec
+    fn spec(spec: serde_json::Value) -> ApplicationSpec {
+        let mut base = json!({ "name": "demo", "image": "nginx:1.23", "deploy": true });
+        base.as_object_mut().unwrap().extend(spec.as_object().unwrap().clone());
+        serde_json::from_value(base).unwrap()
+    }
+
+    #[test]
+    fn image_refs() {
+        for image in [
+            "nginx",
+            "nginx:1.23",
+            "library/nginx:latest",
+            "ghcr.io/org/app:v1.2.3",
+            "localhost:5000/app",
+            "registry.example.com:443/team/app:1_0",
+            "nginx@sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+        ] {
+            assert!(is_valid_image_ref(image), "{} should be valid", image);
+        }
+        for image in ["", "nginx:", "Nginx", "nginx:-tag", "ghcr.io//app", "nginx@sha256:abc", "registry:port/app", "app/"] {
+            assert!(!is_valid_image_ref(image), "{} should be invalid", image);
+        }
+    }
+
+    #[test]
+    fn dns1123_labels() {
+        assert!(is_dns1123_label("my-app-2"));
+        assert!(!is_dns1123_label("My_App"));
+        assert!(!is_dns1123_label("-app"));
+        assert!(!is_dns1123_label(&"a".repeat(64)));
+
+        assert_eq!(sanitize_dns1123_label("My_App"), "my-app");
+        assert_eq!(sanitize_dns1123_label("--web.frontend--"), "web-frontend");
+        assert_eq!(sanitize_dns1123_label("___"), "app");
+        assert_eq!(sanitize_dns1123_label(&"a".repeat(100)).len(), 63);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_five_minutes() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(9), Duration::from_secs(256));
+        assert_eq!(backoff(10), Duration::from_secs(300));
+        assert_eq!(backoff(u32::MAX), Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn slow_reconciles_time_out() {
+        let timeout = Duration::from_millis(10);
+        let slow = with_timeout(timeout, async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(Action::await_change())
+        });
+        assert!(matches!(slow.await, Err(Error::Timeout(t)) if t == timeout));
+
+        let fast = with_timeout(timeout, async { Ok(Action::await_change()) });
+        assert!(fast.await.is_ok());
+    }
+
+    #[test]
+    fn jitter_stays_within_fraction() {
+        let interval = Duration::from_secs(300);
+        assert_eq!(jittered(interval, 0.0), interval);
+        for _ in 0..1000 {
+            let jittered = jittered(interval, 0.1);
+            assert!(jittered >= Duration::from_secs(270) && jittered <= Duration::from_secs(330), "{:?}", jittered);
+        }
+    }
+
+    #[test]
+    fn quantities_accept_numbers() {
+        let spec = spec(json!({
+            "resources": { "requests": { "cpu": 1, "memory": "64Mi" }, "limits": { "cpu": 0.5 } },
+            "env": [{ "name": "CPU", "valueFrom": { "resourceFieldRef": { "resource": "limits.cpu", "divisor": 1 } } }],
+            "volumes": [{ "name": "scratch", "emptyDir": { "sizeLimit": 1000000 } }],
+        }));
+        let resources = spec.resources.unwrap();
+        assert_eq!(resources.requests.unwrap()["cpu"], Quantity("1".into()));
+        assert_eq!(resources.limits.unwrap()["cpu"], Quantity("0.5".into()));
+        let divisor = spec.env.unwrap()[0].value_from.as_ref().and_then(|v| v.resource_field_ref.as_ref()).and_then(|r| r.divisor.clone());
+        assert_eq!(divisor, Some(Quantity("1".into())));
+        let size_limit = spec.volumes.unwrap()[0].empty_dir.as_ref().and_then(|e| e.size_limit.clone());
+        assert_eq!(size_limit, Some(Quantity("1000000".into())));
+    }
+
+    #[test]
+    fn crd_marks_quantities_int_or_string() {
+        use kube::CustomResourceExt;
+        let crd = serde_json::to_value(Application::crd()).unwrap();
+        let spec = &crd["spec"]["versions"][0]["schema"]["openAPIV3Schema"]["properties"]["spec"]["properties"];
+        let cpu = &spec["resources"]["properties"]["requests"]["additionalProperties"];
+        assert_eq!(cpu["x-kubernetes-int-or-string"], json!(true));
+        assert_eq!(cpu.get("type"), None);
+        let size_limit = &spec["volumes"]["items"]["properties"]["emptyDir"]["properties"]["sizeLimit"];
+        assert_eq!(size_limit["x-kubernetes-int-or-string"], json!(true));
+        let divisor = &spec["env"]["items"]["properties"]["valueFrom"]["properties"]["resourceFieldRef"]["properties"]["divisor"];
+        assert_eq!(divisor["x-kubernetes-int-or-string"], json!(true));
+    }
+
+    #[test]
+    fn watch_error_kinds() {
+        let response = |code| kube::core::ErrorResponse { status: "Failure".into(), message: "boom".into(), reason: "Expired".into(), code };
+        assert_eq!(watch_error_kind(&watcher::Error::WatchError(response(410))), "watch_expired");
+        assert_eq!(watch_error_kind(&watcher::Error::WatchError(response(500))), "watch_error");
+        assert_eq!(watch_error_kind(&watcher::Error::InitialListFailed(kube::Error::Api(response(403)))), "initial_list");
+        assert_eq!(watch_error_kind(&watcher::Error::TooManyObjects), "too_many_objects");
+    }
+
+    /// Application `ns/name` created at `created` with `spec` merged over a minimal valid spec
+    fn application(ns: &str, name: &str, created: &str, spec_json: serde_json::Value) -> Arc<Application> {
+        let mut app = Application::new(name, spec(spec_json));
+        app.metadata.namespace = Some(ns.into());
+        app.metadata.uid = Some(format!("{}-{}", ns, name));
+        app.metadata.creation_timestamp = Some(Time(created.parse().unwrap()));
+        Arc::new(app)
+    }
+
+    /// Requests a fake apiserver received as `METHOD uri` with their JSON body
+    type Requests = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    /// Client of a fake apiserver answering with `respond(method, path, body)`, recording every request
+    fn fake_apiserver<F>(respond: F) -> (Client, Requests)
+    where
+        F: Fn(&hyper::Method, &str, serde_json::Value) -> (u16, serde_json::Value) + Clone + Send + 'static,
+    {
+        let requests = Requests::default();
+        let recorded = requests.clone();
+        let service = tower::service_fn(move |request: hyper::Request<hyper::Body>| {
+            let (respond, recorded) = (respond.clone(), recorded.clone());
+            async move {
+                let (parts, body) = request.into_parts();
+                let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+                let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+                recorded.lock().unwrap().push((format!("{} {}", parts.method, parts.uri), body.clone()));
+                let (status, response) = respond(&parts.method, parts.uri.path(), body);
+                Ok::<_, std::convert::Infallible>(hyper::Response::builder().status(status).body(hyper::Body::from(response.to_string())).unwrap())
+            }
+        });
+        (Client::new(service, "apps"), requests)
+    }
+
+    /// Answers like an apiserver storing `app` and the `children` named after it by plural resource
+    ///
+    /// Lists are empty, missing objects 404 and writes echo their body
+    fn cluster(app: &Application, children: Vec<(&'static str, serde_json::Value)>) -> impl Fn(&hyper::Method, &str, serde_json::Value) -> (u16, serde_json::Value) + Clone + Send + 'static {
+        let app = serde_json::to_value(app).unwrap();
+        let children: HashMap<_, _> = children.into_iter().collect();
+        move |method, path, body| {
+            let segments: Vec<&str> = path.trim_end_matches("/status").rsplit('/').collect();
+            let not_found = json!({ "apiVersion": "v1", "kind": "Status", "status": "Failure", "reason": "NotFound", "message": "not found", "code": 404 });
+            match (method, segments.as_slice()) {
+                (_, [_, "applications", ..]) => (200, app.clone()),
+                (&hyper::Method::POST, _) => (201, body),
+                (&hyper::Method::GET, ["pods", ..]) => (200, json!({ "apiVersion": "v1", "kind": "PodList", "metadata": {}, "items": [] })),
+                (&hyper::Method::GET | &hyper::Method::DELETE, [_, resource, ..]) => match children.get(resource) {
+                    Some(child) => (200, child.clone()),
+                    None => (404, not_found),
+                },
+                _ => (200, body),
+            }
+        }
+    }
+
+    /// Context of an operator talking to `client`, with every optional behaviour off
+    fn context(client: Client) -> Context {
+        Context {
+            client,
+            diagnostics: Arc::new(RwLock::new(Diagnostics::new(Vec::new(), true))),
+            metrics: Metrics::new(),
+            failures: Arc::default(),
+            became_ready: Arc::default(),
+            finalizer: CUSTOM_APP_FINALIZER.into(),
+            requeue_interval: Duration::from_secs(300),
+            reconcile_timeout: Duration::from_secs(10),
+            requeue_jitter: 0.0,
+            dry_run: false,
+            status_subresource_missing: Arc::default(),
+            observe_only: false,
+            reconcile_permits: None,
+            limiter: None,
+            limits: SpecLimits { max_env: 100, max_sidecars: 10, max_volumes: 50 },
+            target_namespaces: HashSet::new(),
+            permitted_namespaces: Arc::default(),
+            applications: Vec::new(),
+            default_image: None,
+        }
+    }
+
+    /// Reasons of the events published through `requests`
+    fn event_reasons(requests: &Requests) -> Vec<String> {
+        requests.lock().unwrap().iter()
+            .filter(|(request, _)| request.starts_with("POST /apis/events.k8s.io/"))
+            .map(|(_, event)| event["reason"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    /// `METHOD uri` of the requests received, without their bodies
+    fn request_lines(requests: &Requests) -> Vec<String> {
+        requests.lock().unwrap().iter().map(|(request, _)| request.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn paused_applications_only_record_the_pause() {
+        let mut paused = (*application("apps", "demo", "2024-01-01T00:00:00Z", json!({}))).clone();
+        paused.annotations_mut().insert(PAUSED_ANNOTATION.into(), "true".into());
+        let (client, requests) = fake_apiserver(cluster(&paused, Vec::new()));
+        let ctx = Arc::new(context(client));
+
+        paused.reconcile(ctx.clone(), "apps").await.unwrap();
+        assert_eq!(event_reasons(&requests), vec![reasons::PAUSED]);
+        let status_patches: Vec<_> = requests.lock().unwrap().iter().filter(|(request, _)| request.starts_with("PATCH ")).cloned().collect();
+        assert_eq!(status_patches.len(), 1);
+        let (request, body) = &status_patches[0];
+        assert!(request.starts_with("PATCH /apis/per.naess/v1alpha1/namespaces/apps/applications/demo/status?"), "{}", request);
+        assert_eq!(body["status"]["state"], "Starting");
+        assert_eq!(body["status"]["deployed"], false);
+        assert_eq!(body["status"]["conditions"][0]["type"], "Paused");
+        assert!(request_lines(&requests).iter().all(|request| request.starts_with("POST /apis/events.k8s.io/") || request.contains("/applications/demo")));
+
+        // Once recorded the pause makes no calls at all
+        requests.lock().unwrap().clear();
+        paused.status = Some(serde_json::from_value(body["status"].clone()).unwrap());
+        paused.reconcile(ctx, "apps").await.unwrap();
+        assert!(request_lines(&requests).is_empty());
+    }
+
+    #[tokio::test]
+    async fn dry_run_creates_nothing() {
+        let app = application("apps", "demo", "2024-01-01T00:00:00Z", json!({ "expose": { "port": 80 }, "pdb": { "min_available": 1 } }));
+        let (client, requests) = fake_apiserver(cluster(&app, Vec::new()));
+        let ctx = Arc::new(Context { dry_run: true, ..context(client) });
+
+        app.reconcile(ctx, "apps").await.unwrap();
+        let requests = request_lines(&requests);
+        assert!(requests.iter().all(|request| !request.starts_with("POST ")), "{:?}", requests);
+        let patches: Vec<_> = requests.iter().filter(|request| request.starts_with("PATCH ")).collect();
+        assert_eq!(patches.len(), 3, "{:?}", patches);
+        assert!(patches.iter().all(|patch| patch.contains("dryRun=All")), "{:?}", patches);
+    }
+
+    /// Child of the Application `apps/demo` as stored by the apiserver
+    fn managed_child(api_version: &str, kind: &str) -> serde_json::Value {
+        json!({
+            "apiVersion": api_version,
+            "kind": kind,
+            "metadata": {
+                "name": "demo",
+                "namespace": "apps",
+                "labels": { "app.kubernetes.io/managed-by": MANAGED_BY },
+                "annotations": { OWNER_ANNOTATION: "apps/demo" },
+            },
+        })
+    }
+
+    /// Plural resources deleted through `requests`, in order
+    fn deleted_resources(requests: &Requests) -> Vec<String> {
+        request_lines(requests).iter()
+            .filter_map(|request| request.strip_prefix("DELETE "))
+            .filter_map(|uri| uri.split('?').next()?.rsplit('/').nth(1).map(String::from))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn cleanup_deletes_every_child() {
+        let mut app = (*application("apps", "demo", "2024-01-01T00:00:00Z", json!({}))).clone();
+        app.status = Some(serde_json::from_value(json!({ "state": "Running", "deployed": true })).unwrap());
+        let children = vec![
+            ("ingresses", managed_child("networking.k8s.io/v1", "Ingress")),
+            ("poddisruptionbudgets", managed_child("policy/v1", "PodDisruptionBudget")),
+            ("horizontalpodautoscalers", managed_child("autoscaling/v2", "HorizontalPodAutoscaler")),
+            ("services", managed_child("v1", "Service")),
+            ("deployments", managed_child("apps/v1", "Deployment")),
+        ];
+        let (client, requests) = fake_apiserver(cluster(&app, children));
+
+        app.cleanup(Arc::new(context(client)), "apps").await.unwrap();
+        assert_eq!(deleted_resources(&requests), vec!["ingresses", "poddisruptionbudgets", "horizontalpodautoscalers", "services", "deployments"]);
+        assert_eq!(event_reasons(&requests), vec![reasons::DELETING_DEPLOYMENT, reasons::DELETE_APPLICATION]);
+    }
+
+    #[tokio::test]
+    async fn cleanup_skips_the_deployment_of_never_deployed_applications() {
+        let app = application("apps", "demo", "2024-01-01T00:00:00Z", json!({ "deploy": false }));
+        let (client, requests) = fake_apiserver(cluster(&app, vec![("deployments", managed_child("apps/v1", "Deployment"))]));
+
+        app.cleanup(Arc::new(context(client)), "apps").await.unwrap();
+        assert!(request_lines(&requests).iter().all(|request| !request.contains("/deployments/")), "{:?}", request_lines(&requests));
+        assert_eq!(event_reasons(&requests), vec![reasons::DELETE_APPLICATION]);
+    }
+
+    #[tokio::test]
+    async fn cleanup_tolerates_an_already_deleted_deployment() {
+        let mut app = (*application("apps", "demo", "2024-01-01T00:00:00Z", json!({}))).clone();
+        app.status = Some(serde_json::from_value(json!({ "state": "Running", "deployed": true })).unwrap());
+        // Found, then gone by the time it is deleted
+        let deployment = managed_child("apps/v1", "Deployment");
+        let respond = cluster(&app, vec![("deployments", deployment)]);
+        let (client, requests) = fake_apiserver(move |method: &hyper::Method, path: &str, body| match (method, path.ends_with("/deployments/demo")) {
+            (&hyper::Method::DELETE, true) => (404, json!({ "apiVersion": "v1", "kind": "Status", "status": "Failure", "reason": "NotFound", "message": "not found", "code": 404 })),
+            _ => respond(method, path, body),
+        });
+
+        app.cleanup(Arc::new(context(client)), "apps").await.unwrap();
+        assert_eq!(deleted_resources(&requests), vec!["deployments"]);
+        assert_eq!(event_reasons(&requests), vec![reasons::DELETE_APPLICATION]);
+    }
+
+    #[tokio::test]
+    async fn observe_only_never_mutates_children() {
+        let mut app = (*application("apps", "demo", "2024-01-01T00:00:00Z", json!({ "expose": { "port": 80 }, "pdb": { "min_available": 1 } }))).clone();
+        app.status = Some(serde_json::from_value(json!({ "state": "Running", "deployed": true })).unwrap());
+        let (client, requests) = fake_apiserver(cluster(&app, vec![("deployments", managed_child("apps/v1", "Deployment"))]));
+        let ctx = Arc::new(Context { observe_only: true, ..context(client) });
+        let child_mutations = |requests: &Requests| -> Vec<String> {
+            request_lines(requests).into_iter()
+                .filter(|request| !request.starts_with("GET ") && !request.contains("/applications/demo") && !request.starts_with("POST /apis/events.k8s.io/"))
+                .collect()
+        };
+
+        app.reconcile(ctx.clone(), "apps").await.unwrap();
+        assert_eq!(child_mutations(&requests), Vec::<String>::new());
+        let status_patch = requests.lock().unwrap().iter().find(|(request, _)| request.starts_with("PATCH ") && request.contains("/applications/demo/status")).cloned();
+        let (_, body) = status_patch.expect("observe only still writes the status");
+        assert_eq!(body["status"]["deployed"], true);
+
+        requests.lock().unwrap().clear();
+        app.cleanup(ctx, "apps").await.unwrap();
+        assert_eq!(child_mutations(&requests), Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn status_is_patched_on_the_object_without_status_subresource() {
+        let app = application("apps", "demo", "2024-01-01T00:00:00Z", json!({ "deploy": false }));
+        let respond = cluster(&app, Vec::new());
+        let (client, requests) = fake_apiserver(move |method: &hyper::Method, path: &str, body| match (method, path.ends_with("/status")) {
+            (&hyper::Method::PATCH, true) => (404, json!({ "apiVersion": "v1", "kind": "Status", "status": "Failure", "reason": "NotFound", "message": "not found", "code": 404 })),
+            _ => respond(method, path, body),
+        });
+        let ctx = Arc::new(context(client));
+        let application_requests = |requests: &Requests| -> Vec<String> {
+            request_lines(requests).into_iter()
+                .filter(|request| request.contains("/applications/demo"))
+                .map(|request| request.split('?').next().unwrap_or_default().to_string())
+                .collect()
+        };
+
+        app.reconcile(ctx.clone(), "apps").await.unwrap();
+        assert!(ctx.status_subresource_missing.load(Ordering::Relaxed));
+        let path = "/apis/per.naess/v1alpha1/namespaces/apps/applications/demo";
+        assert_eq!(application_requests(&requests), vec![format!("PATCH {}/status", path), format!("GET {}", path), format!("PATCH {}", path)]);
+
+        // Later status patches go to the object right away
+        requests.lock().unwrap().clear();
+        app.reconcile(ctx, "apps").await.unwrap();
+        assert_eq!(application_requests(&requests), vec![format!("PATCH {}", path)]);
+    }
+
+    #[test]
+    fn reconcile_reasons() {
+        let mut app = (*application("apps", "demo", "2024-01-01T00:00:00Z", json!({}))).clone();
+        app.metadata.generation = Some(1);
+        assert_eq!(reconcile_reason(&app, false), "created");
+        // Reconciled again once the finalizer was added, before any status was written
+        assert_eq!(reconcile_reason(&app, true), "unknown");
+
+        app.status = Some(serde_json::from_value(json!({ "state": "Running", "deployed": true, "observedGeneration": 1 })).unwrap());
+        assert_eq!(reconcile_reason(&app, true), "unknown");
+        app.metadata.generation = Some(2);
+        assert_eq!(reconcile_reason(&app, true), "spec_changed");
+
+        app.metadata.deletion_timestamp = Some(Time(Utc::now()));
+        assert_eq!(reconcile_reason(&app, true), "deleted");
+    }
+
+    #[test]
+    fn observed_status_is_unchanged_by_a_no_op_reconcile() {
+        let mut app = (*application("apps", "demo", "2024-01-01T00:00:00Z", json!({ "replicas": 2 }))).clone();
+        app.metadata.generation = Some(3);
+        let status = app.observed_status(ApplicationState::Running, true, Some(2), None, None);
+        assert_eq!(status.observed_generation, Some(3));
+        assert_eq!(status.selector.as_deref(), Some("app.kubernetes.io/managed-by=customapps-operator,app.kubernetes.io/name=demo"));
+        assert_eq!(status.replicas_source.as_deref(), Some("Spec"));
+
+        app.status = Some(status.clone());
+        assert_eq!(app.observed_status(ApplicationState::Running, true, Some(2), None, None), status);
+        assert_ne!(app.observed_status(ApplicationState::Starting, true, Some(1), None, None), status);
+    }
+
+    #[tokio::test]
+    async fn no_op_reconciles_do_not_patch_the_status() {
+        let mut app = (*application("apps", "demo", "2024-01-01T00:00:00Z", json!({ "deploy": false }))).clone();
+        let (client, requests) = fake_apiserver(cluster(&app, Vec::new()));
+        let ctx = Arc::new(context(client));
+        let status_patch = |requests: &Requests| {
+            requests.lock().unwrap().iter().find(|(request, _)| request.starts_with("PATCH ") && request.contains("/status")).map(|(_, body)| body.clone())
+        };
+
+        app.reconcile(ctx.clone(), "apps").await.unwrap();
+        let applied = status_patch(&requests).expect("the first reconcile writes the status");
+        app.status = Some(serde_json::from_value(applied["status"].clone()).unwrap());
+        requests.lock().unwrap().clear();
+
+        app.reconcile(ctx, "apps").await.unwrap();
+        assert_eq!(status_patch(&requests), None);
+    }
+
+    #[test]
+    fn name_owner_is_the_oldest_application() {
+        let first = application("apps", "first", "2024-01-01T00:00:00Z", json!({}));
+        let second = application("apps", "second", "2024-01-02T00:00:00Z", json!({}));
+        let apps = vec![first.clone(), second.clone()];
+        assert_eq!(first.name_owner("apps", apps.clone()), None);
+        assert_eq!(second.name_owner("apps", apps.clone()), Some("apps/first".into()));
+
+        let renamed = application("apps", "second", "2024-01-02T00:00:00Z", json!({ "name": "other" }));
+        assert_eq!(renamed.name_owner("apps", vec![first, renamed.clone()]), None);
+    }
+
+    #[test]
+    fn name_owner_compares_target_namespaces() {
+        let first = application("team-a", "first", "2024-01-01T00:00:00Z", json!({ "target_namespace": "shared" }));
+        let second = application("shared", "second", "2024-01-02T00:00:00Z", json!({}));
+        let elsewhere = application("team-b", "third", "2024-01-03T00:00:00Z", json!({}));
+        let apps = vec![first.clone(), second.clone(), elsewhere.clone()];
+        assert_eq!(second.name_owner("shared", apps.clone()), Some("team-a/first".into()));
+        assert_eq!(elsewhere.name_owner("team-b", apps), None);
+    }
+
+    #[test]
+    fn target_namespaces_must_be_allowed() {
+        let allowed = HashSet::from(["shared".to_string()]);
+        assert_eq!(allowed_target_namespace(&allowed, "apps", "apps"), Ok(()));
+        assert_eq!(allowed_target_namespace(&allowed, "apps", "shared"), Ok(()));
+        assert!(allowed_target_namespace(&allowed, "apps", "kube-system").is_err());
+        assert!(allowed_target_namespace(&HashSet::new(), "apps", "shared").is_err());
+    }
+
+    #[test]
+    fn validate_accepts_minimal_spec() {
+        assert_eq!(spec(json!({})).validate(None), Ok(()));
+    }
+
+    #[test]
+    fn image_falls_back_to_the_default_image() {
+        let with_image = spec(json!({}));
+        assert_eq!(with_image.image(Some("busybox:1.36")).as_deref(), Some("nginx:1.23"));
+
+        let without_image: ApplicationSpec = serde_json::from_value(json!({ "name": "demo", "deploy": true })).unwrap();
+        assert_eq!(without_image.image(Some("busybox:1.36")).as_deref(), Some("busybox:1.36"));
+        assert_eq!(without_image.validate(Some("busybox:1.36")), Ok(()));
+
+        assert_eq!(without_image.image(None), None);
+        assert!(without_image.validate(None).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_fields() {
+        for (field, value) in [
+            ("image", json!("nginx:")),
+            ("container_name", json!("My_App")),
+            ("replicas", json!(-1)),
+            ("termination_grace_period_seconds", json!(-1)),
+            ("image_pull_policy", json!("Sometimes")),
+            ("ingress", json!({ "host": "demo.example.com" })),
+            ("pdb", json!({})),
+            ("pdb", json!({ "min_available": 1, "max_unavailable": 1 })),
+        ] {
+            let spec = spec(json!({ field: value }));
+            assert!(spec.validate(None).is_err(), "{} = {} should be rejected", field, value);
+        }
+    }
+
+    #[test]
+    fn validate_requires_unique_container_names() {
+        let sidecar = json!({ "name": "proxy", "image": "envoyproxy/envoy:v1.24.0" });
+        assert_eq!(spec(json!({ "sidecars": [sidecar] })).validate(None), Ok(()));
+        assert!(spec(json!({ "sidecars": [sidecar, sidecar] })).validate(None).is_err());
+        assert!(spec(json!({ "sidecars": [{ "name": "demo", "image": "busybox" }] })).validate(None).is_err());
+        assert!(spec(json!({ "sidecars": [{ "name": "", "image": "busybox" }] })).validate(None).is_err());
+        assert!(spec(json!({ "sidecars": [sidecar], "init_containers": [sidecar] })).validate(None).is_err());
+        assert!(spec(json!({ "init_containers": [{ "name": "migrate", "image": "not an image" }] })).validate(None).is_err());
+    }
+
+    #[test]
+    fn validate_requires_mounted_volumes() {
+        let secret = json!({ "name": "tls", "secret": { "secretName": "demo-tls" } });
+        let mount = json!({ "name": "tls", "mountPath": "/etc/tls" });
+        assert_eq!(spec(json!({ "volumes": [secret], "volume_mounts": [mount] })).validate(None), Ok(()));
+        assert!(spec(json!({ "volume_mounts": [mount] })).validate(None).is_err());
+        assert!(spec(json!({ "volumes": [secret, secret] })).validate(None).is_err());
+
+        let config_mount = json!({ "config_map": "demo-config", "mount_path": "/etc/demo" });
+        let config = json!({ "name": CONFIG_VOLUME, "emptyDir": {} });
+        assert!(spec(json!({ "config_mount": config_mount, "volumes": [config] })).validate(None).is_err());
+        let mount_config = json!({ "name": CONFIG_VOLUME, "mountPath": "/etc/other" });
+        assert_eq!(spec(json!({ "config_mount": config_mount, "volume_mounts": [mount_config] })).validate(None), Ok(()));
+    }
+
+    #[test]
+    fn spec_limits_reject_oversized_specs() {
+        let limits = SpecLimits { max_env: 2, max_sidecars: 1, max_volumes: 1 };
+        let env = |n: usize| (0..n).map(|i| json!({ "name": format!("VAR_{}", i), "value": "x" })).collect::<Vec<_>>();
+        assert_eq!(limits.check(&spec(json!({ "env": env(2) }))), Ok(()));
+        assert!(limits.check(&spec(json!({ "env": env(3) }))).is_err());
+
+        let sidecar = |name: &str| json!({ "name": name, "image": "busybox" });
+        assert!(limits.check(&spec(json!({ "sidecars": [sidecar("a"), sidecar("b")] }))).is_err());
+
+        let config_mount = json!({ "config_map": "demo-config", "mount_path": "/etc/demo" });
+        assert_eq!(limits.check(&spec(json!({ "config_mount": config_mount }))), Ok(()));
+        let scratch = json!({ "name": "scratch", "emptyDir": {} });
+        assert!(limits.check(&spec(json!({ "config_mount": config_mount, "volumes": [scratch] }))).is_err());
+    }
+
+    #[test]
+    fn metrics_use_separate_registries() {
+        let first = Metrics::new();
+        let second = Metrics::new();
+        first.reconciliations.with_label_values(&["apps", "created"]).inc();
+
+        let reconciliations = |metrics: &Metrics| {
+            metrics.registry
+                .gather()
+                .into_iter()
+                .find(|family| family.get_name() == "app_controller_reconciliations_total")
+                .map_or(0, |family| family.get_metric().len())
+        };
+        assert_eq!(reconciliations(&first), 1);
+        assert_eq!(reconciliations(&second), 0);
+        assert!(second.registry.gather().iter().any(|family| family.get_name() == "app_controller_build_info"));
+    }
+
+    #[tokio::test]
+    async fn metrics_are_labelled_with_the_namespace() {
+        let app = application("apps", "demo", "2024-01-01T00:00:00Z", json!({}));
+        let (client, _) = fake_apiserver(cluster(&app, Vec::new()));
+        let ctx = Arc::new(context(client));
+
+        reconcile(app, ctx.clone()).await.unwrap();
+        let error = ReconcileError { namespace: "apps".into(), name: "demo".into(), source: Error::MissingNamespace };
+        error_policy(&error, ctx.clone());
+
+        let families = ctx.metrics.registry.gather();
+        for name in ["app_controller_reconciliations_total", "app_controller_reconciliation_errors_total", "app_controller_reconcile_duration_seconds"] {
+            let family = families.iter().find(|family| family.get_name() == name).unwrap_or_else(|| panic!("{} is not gathered", name));
+            let metric = &family.get_metric()[0];
+            let namespace = metric.get_label().iter().find(|label| label.get_name() == "namespace");
+            assert_eq!(namespace.map(|label| label.get_value()), Some("apps"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn diagnostics_serialize_times_as_rfc3339() {
+        let diagnostics = Diagnostics::new(vec!["apps".into()], true);
+        let serialized = serde_json::to_value(&diagnostics).unwrap();
+        for (field, time) in [("last_event", diagnostics.last_event), ("started_at", diagnostics.started_at)] {
+            let rfc3339 = serialized[field].as_str().unwrap();
+            assert_eq!(DateTime::parse_from_rfc3339(rfc3339).unwrap(), time, "{}", field);
+        }
+        assert!(serialized.get("reporter").is_none());
+        assert_eq!(serialized["watch_namespaces"], json!(["apps"]));
+        assert_eq!(serialized["leader"], true);
+    }
+
+    #[test]
+    fn version_is_the_crate_version() {
+        assert_eq!(Diagnostics::new(Vec::new(), true).version, env!("CARGO_PKG_VERSION"));
+
+        let build_info = Metrics::new()
+            .registry
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == "app_controller_build_info")
+            .unwrap();
+        let labels: HashMap<_, _> = build_info.get_metric()[0]
+            .get_label()
+            .iter()
+            .map(|label| (label.get_name(), label.get_value()))
+            .collect();
+        assert_eq!(labels["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(labels["git_sha"], GIT_SHA);
+    }
+
+    #[test]
+    fn reporter_instance_is_the_pod_name() {
+        // The only test touching these variables, so it cannot race another
+        std::env::set_var("POD_NAME", "customapps-operator-7d9f8c6b5-x2x8k");
+        std::env::set_var("HOSTNAME", "customapps-operator-host");
+        assert_eq!(instance_identity().as_deref(), Some("customapps-operator-7d9f8c6b5-x2x8k"));
+        assert_eq!(Diagnostics::new(Vec::new(), true).reporter.instance.as_deref(), Some("customapps-operator-7d9f8c6b5-x2x8k"));
+
+        std::env::remove_var("POD_NAME");
+        assert_eq!(instance_identity().as_deref(), Some("customapps-operator-host"));
+        assert_eq!(Diagnostics::new(Vec::new(), true).reporter.instance.as_deref(), Some("customapps-operator-host"));
+
+        std::env::remove_var("HOSTNAME");
+        assert_eq!(instance_identity(), None);
+        let reporter = Diagnostics::new(Vec::new(), true).reporter;
+        assert_eq!(reporter.instance, None);
+        assert_eq!(reporter.controller, "app-reporter");
+    }
+
+    fn deployment_status(status: serde_json::Value) -> DeploymentStatus {
+        serde_json::from_value(status).unwrap()
+    }
+
+    #[test]
+    fn deployment_state_from_status() {
+        let rolling_out = deployment_status(json!({ "replicas": 3, "updatedReplicas": 3, "readyReplicas": 1 }));
+        assert_eq!(deployment_state(3, &rolling_out), ApplicationState::Starting);
+
+        let old_pods_ready = deployment_status(json!({ "replicas": 4, "updatedReplicas": 1, "readyReplicas": 3 }));
+        assert_eq!(deployment_state(3, &old_pods_ready), ApplicationState::Starting);
+
+        let available = deployment_status(json!({ "replicas": 3, "updatedReplicas": 3, "readyReplicas": 3, "availableReplicas": 3 }));
+        assert_eq!(deployment_state(3, &available), ApplicationState::Running);
+        assert_eq!(deployment_state(0, &DeploymentStatus::default()), ApplicationState::Running);
+
+        let stalled = deployment_status(json!({
+            "replicas": 3,
+            "updatedReplicas": 3,
+            "readyReplicas": 0,
+            "conditions": [{ "type": "Progressing", "status": "False", "reason": "ProgressDeadlineExceeded" }]
+        }));
+        assert_eq!(deployment_state(3, &stalled), ApplicationState::Failed);
+        assert!(progress_deadline_exceeded(&stalled));
+        assert!(!progress_deadline_exceeded(&rolling_out));
+
+        let quota_exceeded = deployment_status(json!({
+            "conditions": [{ "type": "ReplicaFailure", "status": "True", "reason": "FailedCreate" }]
+        }));
+        assert_eq!(deployment_state(3, &quota_exceeded), ApplicationState::Failed);
+    }
+
+    fn condition_status<'a>(conditions: &'a [Condition], type_: &str) -> (&'a str, &'a str) {
+        let condition = conditions.iter().find(|c| c.type_ == type_).unwrap();
+        (condition.status.as_str(), condition.reason.as_str())
+    }
+
+    #[test]
+    fn conditions_follow_state() {
+        let running = status_conditions(&ApplicationState::Running, true, None, None, Some(2), &[]);
+        assert_eq!(condition_status(&running, "Available"), ("True", "Running"));
+        assert_eq!(condition_status(&running, "Progressing"), ("False", "Running"));
+        assert_eq!(condition_status(&running, "Degraded"), ("False", "Running"));
+        assert!(running.iter().all(|c| c.observed_generation == Some(2)));
+
+        let starting = status_conditions(&ApplicationState::Starting, true, None, None, Some(2), &[]);
+        assert_eq!(condition_status(&starting, "Progressing"), ("True", "Starting"));
+
+        let invalid = status_conditions(&ApplicationState::Failed, true, Some("bad spec"), None, Some(2), &[]);
+        assert_eq!(condition_status(&invalid, "Degraded"), ("True", "InvalidSpec"));
+
+        let crash_loop = PodFailure { reason: reasons::CRASH_LOOP_BACK_OFF, message: "Container `demo` is crash looping".into() };
+        let crash_looping = status_conditions(&ApplicationState::Failed, true, None, Some(&crash_loop), Some(2), &[]);
+        assert_eq!(condition_status(&crash_looping, "Degraded"), ("True", "CrashLoopBackOff"));
+
+        let undeployed = status_conditions(&ApplicationState::Running, false, None, None, Some(2), &[]);
+        assert_eq!(condition_status(&undeployed, "Available"), ("False", "NotDeployed"));
+    }
+
+    #[test]
+    fn conditions_keep_transition_time_while_unchanged() {
+        let earlier = Time(Utc::now() - chrono::Duration::hours(1));
+        let mut previous = status_conditions(&ApplicationState::Running, true, None, None, Some(1), &[]);
+        previous.iter_mut().for_each(|c| c.last_transition_time = earlier.clone());
+
+        let still_running = status_conditions(&ApplicationState::Running, true, None, None, Some(2), &previous);
+        assert!(still_running.iter().all(|c| c.last_transition_time == earlier));
+
+        let failed = status_conditions(&ApplicationState::Failed, true, None, None, Some(2), &previous);
+        assert_ne!(failed.iter().find(|c| c.type_ == "Available").unwrap().last_transition_time, earlier);
+        assert_eq!(failed.iter().find(|c| c.type_ == "Progressing").unwrap().last_transition_time, earlier);
+    }
+
+    #[test]
+    fn status_patch_applies_only_the_status() {
+        let status: ApplicationStatus = serde_json::from_value(json!({
+            "state": "Running",
+            "deployed": true,
+            "observedGeneration": 2,
+            "replicas": 3,
+            "selector": "app.kubernetes.io/name=demo",
+            "replicasSource": "Spec"
+        }))
+        .unwrap();
+        let Patch::Apply(body) = status.apply_patch() else {
+            panic!("status is server-side applied");
+        };
+        let expected = json!({
+            "apiVersion": "per.naess/v1alpha1",
+            "kind": "Application",
+            "status": {
+                "state": "Running",
+                "deployed": true,
+                "observedGeneration": 2,
+                "replicas": 3,
+                "selector": "app.kubernetes.io/name=demo",
+                "conditions": [],
+                "replicasSource": "Spec"
+            }
+        });
+        assert_eq!(serde_json::to_value(body).unwrap(), expected);
+    }
+}
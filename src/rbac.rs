@@ -0,0 +1,114 @@
+use k8s_openapi::api::authorization::v1::{ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec};
+use kube::{api::PostParams, Api, Client};
+use tracing::info;
+
+use crate::Error;
+
+/// `(group, resource, subresource, verb)` the operator needs in every watched namespace
+const WATCH_PERMISSIONS: &[(&str, &str, Option<&str>, &str)] = &[
+    ("per.naess", "applications", None, "get"),
+    ("per.naess", "applications", None, "list"),
+    ("per.naess", "applications", None, "watch"),
+    ("per.naess", "applications", None, "patch"),
+    ("per.naess", "applications", Some("status"), "patch"),
+    ("apps", "deployments", None, "list"),
+    ("apps", "deployments", None, "watch"),
+    ("events.k8s.io", "events", None, "create"),
+];
+
+/// `(group, resource, subresource, verb)` the operator needs wherever it manages children
+///
+/// Children are server-side applied, so `patch` covers creating them
+const CHILD_PERMISSIONS: &[(&str, &str, Option<&str>, &str)] = &[
+    ("apps", "deployments", None, "get"),
+    ("apps", "deployments", None, "patch"),
+    ("apps", "deployments", None, "delete"),
+    ("", "services", None, "get"),
+    ("", "services", None, "patch"),
+    ("", "services", None, "delete"),
+    ("networking.k8s.io", "ingresses", None, "get"),
+    ("networking.k8s.io", "ingresses", None, "patch"),
+    ("networking.k8s.io", "ingresses", None, "delete"),
+    ("autoscaling", "horizontalpodautoscalers", None, "get"),
+    ("autoscaling", "horizontalpodautoscalers", None, "patch"),
+    ("autoscaling", "horizontalpodautoscalers", None, "delete"),
+    ("policy", "poddisruptionbudgets", None, "get"),
+    ("policy", "poddisruptionbudgets", None, "patch"),
+    ("policy", "poddisruptionbudgets", None, "delete"),
+    ("", "pods", None, "list"),
+];
+
+/// `(group, resource, subresource, verb)` needed on the Lease when `LEADER_ELECTION` is enabled
+const LEASE_PERMISSIONS: &[(&str, &str, Option<&str>, &str)] = &[
+    ("coordination.k8s.io", "leases", None, "get"),
+    ("coordination.k8s.io", "leases", None, "create"),
+    ("coordination.k8s.io", "leases", None, "update"),
+];
+
+/// Ask the apiserver whether the operator's own identity may do `verb` on a resource
+pub async fn can_i(client: Client, namespace: Option<&str>, group: &str, resource: &str, subresource: Option<&str>, verb: &str) -> Result<bool, kube::Error> {
+    let review = SelfSubjectAccessReview {
+        spec: SelfSubjectAccessReviewSpec {
+            resource_attributes: Some(ResourceAttributes {
+                namespace: namespace.map(String::from),
+                group: Some(group.into()),
+                resource: Some(resource.into()),
+                subresource: subresource.map(String::from),
+                verb: Some(verb.into()),
+                ..ResourceAttributes::default()
+            }),
+            ..SelfSubjectAccessReviewSpec::default()
+        },
+        ..SelfSubjectAccessReview::default()
+    };
+
+    let reviews: Api<SelfSubjectAccessReview> = Api::all(client);
+    let review = reviews.create(&PostParams::default(), &review).await?;
+    Ok(review.status.map(|s| s.allowed).unwrap_or(false))
+}
+
+/// Verify the RBAC rules needed for watching and reconciling, `None` checks cluster wide
+///
+/// The error names every missing verb and resource
+pub async fn check_permissions(client: Client, namespace: Option<&str>) -> Result<(), Error> {
+    let permissions: Vec<_> = WATCH_PERMISSIONS.iter().chain(CHILD_PERMISSIONS).copied().collect();
+    check(client, namespace, &permissions).await
+}
+
+/// Verify the RBAC rules needed for managing children in a namespace outside the watched ones
+pub async fn check_child_permissions(client: Client, namespace: &str) -> Result<(), Error> {
+    check(client, Some(namespace), CHILD_PERMISSIONS).await
+}
+
+/// Verify the RBAC rules needed for taking and renewing the leader Lease in `namespace`
+pub async fn check_lease_permissions(client: Client, namespace: &str) -> Result<(), Error> {
+    check(client, Some(namespace), LEASE_PERMISSIONS).await
+}
+
+async fn check(client: Client, namespace: Option<&str>, permissions: &[(&str, &str, Option<&str>, &str)]) -> Result<(), Error> {
+    let mut missing = Vec::new();
+    for (group, resource, subresource, verb) in permissions {
+        let allowed = can_i(client.clone(), namespace, group, resource, *subresource, verb)
+            .await
+            .map_err(Error::KubeError)?;
+        if !allowed {
+            let resource = match subresource {
+                Some(sub) => format!("{}/{}", resource, sub),
+                None => resource.to_string(),
+            };
+            let resource = if group.is_empty() { resource } else { format!("{}.{}", resource, group) };
+            missing.push(format!("{} {}", verb, resource));
+        }
+    }
+
+    if missing.is_empty() {
+        info!("RBAC permissions verified in {}", namespace.unwrap_or("all namespaces"));
+        Ok(())
+    } else {
+        Err(Error::MissingPermissions(format!(
+            "{} in {}",
+            missing.join(", "),
+            namespace.unwrap_or("all namespaces")
+        )))
+    }
+}
@@ -12,3 +12,37 @@ pub fn get_trace_id() -> TraceId {
         .trace_id()
 }
 
+
+/// Default collector address when `OTEL_EXPORTER_OTLP_ENDPOINT` is unset
+#[cfg(feature = "telemetry")]
+pub const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4317";
+
+/// Install an OTLP batch exporter and return its tracer
+///
+/// The collector is read from `OTEL_EXPORTER_OTLP_ENDPOINT` and the fraction of
+/// sampled traces from `OTEL_TRACES_SAMPLER_ARG` (defaults to 1.0)
+#[cfg(feature = "telemetry")]
+pub async fn init_tracer() -> Result<opentelemetry::sdk::trace::Tracer, opentelemetry::trace::TraceError> {
+    use opentelemetry::{sdk::{trace, Resource}, KeyValue};
+    use opentelemetry_otlp::WithExportConfig;
+
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").unwrap_or_else(|_| DEFAULT_OTLP_ENDPOINT.into());
+    let ratio = match std::env::var("OTEL_TRACES_SAMPLER_ARG") {
+        Ok(ratio) => ratio.parse::<f64>().unwrap_or_else(|_| {
+            // Runs before the tracing subscriber is installed
+            eprintln!("Invalid OTEL_TRACES_SAMPLER_ARG `{}`, sampling all traces", ratio);
+            1.0
+        }),
+        Err(_) => 1.0,
+    };
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(
+            trace::config()
+                .with_sampler(trace::Sampler::TraceIdRatioBased(ratio))
+                .with_resource(Resource::new(vec![KeyValue::new("service.name", "rust-kube-operator")])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)
+}